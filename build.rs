@@ -0,0 +1,7 @@
+fn main() {
+    let ac = autocfg::new();
+    // The `ErrorKind`s of `io_error_more`, see `io_status.rs`
+    ac.emit_rustc_version(1, 83);
+
+    autocfg::rerun_path("build.rs");
+}
@@ -0,0 +1,30 @@
+//! Conversion of `anyhow::Error` into [ApiError].
+//!
+//! The whole error including its context becomes the source of the
+//! [ApiError]. The status is taken from the outermost [StatusHint] attached
+//! with [AnyhowStatusExt::with_status]. Without a hint the global
+//! [StatusMapper] classifies the error chain.
+//!
+//! Requires the `anyhow` feature
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! fn load_user(id: u32) -> anyhow::Result<String> {
+//!     Err(anyhow::anyhow!("no user with id {}", id)).with_status(StatusCode::NOT_FOUND)
+//! }
+//!
+//! let err: ApiError = load_user(42).unwrap_err().into();
+//!
+//! assert_eq!(err.status(), StatusCode::NOT_FOUND);
+//! assert_eq!(err.message(), Some("no user with id 42"));
+//! ```
+report_integration! {
+    feature: "anyhow",
+    krate: anyhow,
+    report: Error,
+    context: Context::context,
+    ext: AnyhowStatusExt::with_status,
+}
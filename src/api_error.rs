@@ -7,16 +7,16 @@
 //!
 //! [ApiError] can be converted to an [HttpApiProblem] and
 //! also has many conversions to responses of web framewors implemented.
+use std::backtrace::Backtrace;
 use std::borrow::Cow;
-use std::collections::HashMap;
 use std::fmt::{self, Display};
 use std::io;
+use std::time::Duration;
 
 use std::error::Error;
 
 use http::Extensions;
 use serde::Serialize;
-use serde_json::Value;
 
 use super::*;
 pub use http_api_problem_derive::IntoApiError;
@@ -44,7 +44,7 @@ pub struct ApiErrorBuilder {
 
     /// Additional JSON encodable information. It is up to the server how and if
     /// it adds the given information.
-    pub fields: HashMap<String, Value>,
+    pub fields: FieldMap,
 
     /// Typed extensions for carrying processable data server side
     ///
@@ -53,7 +53,14 @@ pub struct ApiErrorBuilder {
     /// Extensions will not be part of an [HttpApiProblem]
     pub extensions: Extensions,
 
+    /// The source error
+    ///
+    /// Set it with [ApiErrorBuilder::source] so that its type becomes part
+    /// of the [fingerprint](ApiError::fingerprint).
     pub source: Option<Box<dyn Error + Send + Sync + 'static>>,
+
+    /// The type of the source if it was set with [ApiErrorBuilder::source]
+    source_type: Option<&'static str>,
 }
 
 impl ApiErrorBuilder {
@@ -117,10 +124,27 @@ impl ApiErrorBuilder {
         self
     }
 
+    /// Tells clients to retry the request after the given delay.
+    ///
+    /// Adds the field `retry_after` with the delay in whole seconds
+    /// (rounded up) which is evaluated by [HttpApiProblem::retry_advice].
+    pub fn retry_after(self, delay: Duration) -> Self {
+        let secs = delay.as_secs() + u64::from(delay.subsec_nanos() > 0);
+        self.field("retry_after", secs)
+    }
+
+    /// Tells clients explicitly whether the request may be retried.
+    ///
+    /// Adds the field `retryable` which is evaluated by
+    /// [HttpApiProblem::retry_advice].
+    pub fn retryable(self, retryable: bool) -> Self {
+        self.field("retryable", retryable)
+    }
+
     /// Modify the fields values from within a closure
     pub fn with_fields<F>(mut self, f: F) -> Self
     where
-        F: FnOnce(HashMap<String, Value>) -> HashMap<String, Value>,
+        F: FnOnce(FieldMap) -> FieldMap,
     {
         self.fields = f(self.fields);
 
@@ -150,15 +174,25 @@ impl ApiErrorBuilder {
         self
     }
 
+    /// Sets the source error
+    ///
+    /// The type of the source becomes part of the [fingerprint](ApiError::fingerprint).
     pub fn source<E: Error + Send + Sync + 'static>(self, source: E) -> Self {
-        self.source_in_a_box(Box::new(source))
+        let mut builder = self.source_in_a_box(Box::new(source));
+        builder.source_type = Some(std::any::type_name::<E>());
+        builder
     }
 
+    /// Sets an already boxed source error
+    ///
+    /// The type of the source is not known and therefore not part of
+    /// the [fingerprint](ApiError::fingerprint).
     pub fn source_in_a_box<E: Into<Box<dyn Error + Send + Sync + 'static>>>(
         mut self,
         source: E,
     ) -> Self {
         self.source = Some(source.into());
+        self.source_type = None;
         self
     }
 
@@ -173,6 +207,8 @@ impl ApiErrorBuilder {
             fields: self.fields,
             extensions: self.extensions,
             source: self.source,
+            source_type: self.source_type,
+            backtrace: capture_backtrace(self.status),
         }
     }
 }
@@ -194,17 +230,53 @@ impl ApiErrorBuilder {
 /// but there is a `source` error set, `to_string()` of the source will
 /// be used instead. Otherwise nothing will be displayed or set.
 ///
+/// # Backtraces
+///
+/// A [Backtrace] is captured when an [ApiError] with a server error
+/// status (`5xx`) is created. Whether it actually contains any frames is
+/// controlled by the environment variables `RUST_BACKTRACE` and
+/// `RUST_LIB_BACKTRACE` (see [Backtrace::capture]). Changing the status
+/// afterwards does not capture or drop a backtrace. The [Debug] output only
+/// contains the [status](std::backtrace::BacktraceStatus) of the backtrace.
+///
 /// `ApiError` requires the feature `api-error` to be enabled.
-#[derive(Debug)]
 pub struct ApiError {
     status: StatusCode,
     title: Option<String>,
     message: Option<String>,
     instance: Option<String>,
     type_url: Option<String>,
-    fields: HashMap<String, Value>,
+    fields: FieldMap,
     extensions: Extensions,
     source: Option<Box<dyn Error + Send + Sync + 'static>>,
+    source_type: Option<&'static str>,
+    backtrace: Backtrace,
+}
+
+impl fmt::Debug for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiError")
+            .field("status", &self.status)
+            .field("title", &self.title)
+            .field("message", &self.message)
+            .field("instance", &self.instance)
+            .field("type_url", &self.type_url)
+            .field("fields", &self.fields)
+            .field("extensions", &self.extensions)
+            .field("source", &self.source)
+            .field("backtrace", &self.backtrace.status())
+            .finish()
+    }
+}
+
+/// Only server errors capture a backtrace since walking the stack is
+/// expensive and client errors are expected
+fn capture_backtrace(status: StatusCode) -> Backtrace {
+    if status.is_server_error() {
+        Backtrace::capture()
+    } else {
+        Backtrace::disabled()
+    }
 }
 
 impl ApiError {
@@ -216,8 +288,9 @@ impl ApiError {
             message: None,
             type_url: None,
             instance: None,
-            fields: HashMap::default(),
+            fields: FieldMap::default(),
             source: None,
+            source_type: None,
             extensions: Extensions::default(),
         }
     }
@@ -237,15 +310,18 @@ impl ApiError {
 
     /// Create a new instance with the given [StatusCode]
     pub fn new<T: Into<StatusCode>>(status: T) -> Self {
+        let status = status.into();
         Self {
-            status: status.into(),
+            status,
             title: None,
             message: None,
             type_url: None,
             instance: None,
-            fields: HashMap::new(),
+            fields: FieldMap::default(),
             extensions: Extensions::default(),
             source: None,
+            source_type: None,
+            backtrace: capture_backtrace(status),
         }
     }
 
@@ -314,15 +390,23 @@ impl ApiError {
         self.instance.as_deref()
     }
 
+    /// Sets the source error
+    ///
+    /// See [ApiErrorBuilder::source]
     pub fn set_source<E: Error + Send + Sync + 'static>(&mut self, source: E) {
-        self.set_source_in_a_box(Box::new(source))
+        self.set_source_in_a_box(Box::new(source));
+        self.source_type = Some(std::any::type_name::<E>());
     }
 
+    /// Sets an already boxed source error
+    ///
+    /// See [ApiErrorBuilder::source_in_a_box]
     pub fn set_source_in_a_box<E: Into<Box<dyn Error + Send + Sync + 'static>>>(
         &mut self,
         source: E,
     ) {
         self.source = Some(source.into());
+        self.source_type = None;
     }
 
     /// Adds a serializable field. If the serialization fails nothing will be
@@ -364,12 +448,12 @@ impl ApiError {
     }
 
     /// Returns a reference to the serialized fields
-    pub fn fields(&self) -> &HashMap<String, Value> {
+    pub fn fields(&self) -> &FieldMap {
         &self.fields
     }
 
     /// Returns a mutable reference to the serialized fields
-    pub fn fields_mut(&mut self) -> &mut HashMap<String, Value> {
+    pub fn fields_mut(&mut self) -> &mut FieldMap {
         &mut self.fields
     }
 
@@ -387,6 +471,79 @@ impl ApiError {
         &mut self.extensions
     }
 
+    /// Returns a stable fingerprint for the class of this error.
+    ///
+    /// The fingerprint is computed from the status, the type URL, the title
+    /// and the type of the source error. The message and the `instance`
+    /// are not taken into account.
+    ///
+    /// The type of the source is only known if it was set with
+    /// [ApiErrorBuilder::source] or [ApiError::set_source]. All sources
+    /// set as a box, e.g. errors created from a `&str`, a `String`
+    /// or an `anyhow::Error`, are treated as the same type.
+    /// Errors which are only distinguished by such a source collapse
+    /// into one fingerprint. Use [ApiError::fingerprint_with_fields] or
+    /// a custom fingerprint for these. The name of the type is the one
+    /// returned by [std::any::type_name] which may change with
+    /// the compiler version.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let a = ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+    ///     .message("user 1 failed")
+    ///     .finish();
+    /// let b = ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+    ///     .message("user 2 failed")
+    ///     .finish();
+    /// let c = ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+    ///     .source(std::fmt::Error)
+    ///     .finish();
+    ///
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_ne!(a.fingerprint(), c.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint_with_fields(&[])
+    }
+
+    /// Returns a stable fingerprint for the class of this error
+    /// which also takes the given fields into account.
+    ///
+    /// See [ApiError::fingerprint]
+    pub fn fingerprint_with_fields(&self, fields: &[&str]) -> String {
+        let status = self.status.as_u16().to_string();
+
+        let mut fingerprinter = fingerprint::Fingerprinter::new();
+        fingerprinter
+            .add(Some(&status))
+            .add(self.type_url.as_deref())
+            .add(self.title.as_deref())
+            .add_source_type(self.source.is_some(), self.source_type);
+        for field in fields {
+            fingerprinter.add_value(field, self.fields.get(field));
+        }
+
+        fingerprinter.finish()
+    }
+
+    /// Adds the [fingerprint](ApiError::fingerprint) as the field `fingerprint`
+    /// so that it becomes part of the [HttpApiProblem].
+    pub fn expose_fingerprint(&mut self) {
+        let fingerprint = self.fingerprint();
+        self.add_field("fingerprint", fingerprint);
+    }
+
+    /// The [Backtrace] captured when this error was created
+    ///
+    /// Only errors created with a server error status (`5xx`) capture
+    /// a backtrace.
+    pub fn backtrace(&self) -> &Backtrace {
+        &self.backtrace
+    }
+
     /// Creates an [HttpApiProblem] from this.
     ///
     /// Note: If the status is [StatusCode]::UNAUTHORIZED fields will
@@ -464,6 +621,7 @@ impl ApiError {
     /// Requires the `hyper` feature
     #[cfg(feature = "hyper")]
     pub fn into_hyper_response(self) -> hyper::Response<String> {
+        report_server_error(&self);
         let problem = self.into_http_api_problem();
         problem.to_hyper_response()
     }
@@ -473,6 +631,7 @@ impl ApiError {
     /// Requires the `axum` feature
     #[cfg(feature = "axum")]
     pub fn into_axum_response(self) -> axum_core::response::Response {
+        report_server_error(&self);
         let problem = self.into_http_api_problem();
         problem.to_axum_response()
     }
@@ -482,6 +641,7 @@ impl ApiError {
     /// Requires the `actix.web` feature
     #[cfg(feature = "actix-web")]
     pub fn into_actix_web_response(self) -> actix_web::HttpResponse {
+        report_server_error(&self);
         let problem = self.into_http_api_problem();
         problem.into()
     }
@@ -491,6 +651,7 @@ impl ApiError {
     /// Requires the `salvo` feature
     #[cfg(feature = "salvo")]
     pub fn into_salvo_response(self) -> salvo::Response {
+        report_server_error(&self);
         let problem = self.into_http_api_problem();
         problem.to_salvo_response()
     }
@@ -500,6 +661,7 @@ impl ApiError {
     /// Requires the `tide` feature
     #[cfg(feature = "tide")]
     pub fn into_tide_response(self) -> tide::Response {
+        report_server_error(&self);
         let problem = self.into_http_api_problem();
         problem.to_tide_response()
     }
@@ -553,14 +715,133 @@ impl From<ApiError> for HttpApiProblem {
 }
 
 impl From<io::Error> for ApiError {
+    /// The status is determined by the mapper installed with
+    /// [set_io_error_status_mapper]. A `500 - Internal Server Error`
+    /// gets the title "An IO error occurred".
     fn from(error: io::Error) -> Self {
-        ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
-            .title("An IO error occurred")
-            .source(error)
-            .finish()
+        let status = io_error_status(&error);
+        let mut builder = ApiError::builder(status);
+        if status == StatusCode::INTERNAL_SERVER_ERROR {
+            builder = builder.title("An IO error occurred");
+        }
+        builder.source(error).finish()
     }
 }
 
+impl From<serde_json::Error> for ApiError {
+    /// Classifies the error of deserializing a request body
+    ///
+    /// * Invalid JSON and unexpected ends of the input become a
+    ///   `400 - Bad Request` with the position of the error in the
+    ///   fields `line` and `column`
+    /// * JSON which does not match the expected structure becomes a
+    ///   [ValidationProblem] with a single issue. The issue contains the
+    ///   position of the error in the fields `line` and `column`
+    /// * IO errors become a `500 - Internal Server Error`
+    ///
+    /// The position is left out if it is unknown, e.g. for errors of
+    /// [serde_json::from_value].
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    /// use serde_json::json;
+    ///
+    /// let err: ApiError = serde_json::from_str::<Vec<u32>>("[1, \"2\"]")
+    ///     .unwrap_err()
+    ///     .into();
+    ///
+    /// assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    /// assert_eq!(
+    ///     err.fields().get("errors"),
+    ///     Some(&json!([{
+    ///         "detail": "invalid type: string \"2\", expected u32",
+    ///         "line": 1,
+    ///         "column": 7,
+    ///     }]))
+    /// );
+    /// ```
+    fn from(error: serde_json::Error) -> Self {
+        let mut api_error = json_error(&error, None);
+        api_error.set_source(error);
+        api_error
+    }
+}
+
+#[cfg(feature = "serde_path_to_error")]
+impl From<serde_path_to_error::Error<serde_json::Error>> for ApiError {
+    /// Like the conversion of a [serde_json::Error] but additionally adds
+    /// a JSON Pointer to the offending value to the issue of a
+    /// [ValidationProblem].
+    ///
+    /// The pointer is left out if a part of the path is unknown.
+    ///
+    /// Requires the `serde_path_to_error` feature
+    fn from(error: serde_path_to_error::Error<serde_json::Error>) -> Self {
+        use serde_path_to_error::Segment;
+
+        let segments: Option<Vec<String>> = error
+            .path()
+            .iter()
+            .map(|segment| match segment {
+                Segment::Seq { index } => Some(index.to_string()),
+                Segment::Map { key } => Some(key.clone()),
+                Segment::Enum { variant } => Some(variant.clone()),
+                Segment::Unknown => None,
+            })
+            .collect();
+
+        let mut api_error = json_error(error.inner(), segments.map(json_pointer));
+        api_error.set_source(error.into_inner());
+        api_error
+    }
+}
+
+/// Creates the error for a failed deserialization without its source
+fn json_error(error: &serde_json::Error, pointer: Option<String>) -> ApiError {
+    use serde_json::error::Category;
+
+    // Errors of deserializing a `Value` have no position and a line of 0
+    let position = Some((error.line(), error.column())).filter(|(line, _)| *line != 0);
+
+    let title = match error.classify() {
+        Category::Syntax => "The body is not valid JSON",
+        Category::Eof => "The body ended unexpectedly",
+        Category::Data => {
+            let message = error.to_string();
+            let detail = position
+                .and_then(|(line, column)| {
+                    message.strip_suffix(&format!(" at line {} column {}", line, column))
+                })
+                .unwrap_or(&message);
+
+            let mut issue = ValidationIssue::new(detail);
+            issue.pointer = pointer;
+            if let Some((line, column)) = position {
+                issue = issue.field("line", &line).field("column", &column);
+            }
+
+            let mut problem = ValidationProblem::default()
+                .title("The body does not have the expected structure")
+                .issue(issue);
+            problem.type_url = crate::validation::validation_type_url();
+            return problem.into_api_error();
+        }
+        Category::Io => {
+            return ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+                .title("An IO error occurred")
+                .finish()
+        }
+    };
+
+    let mut builder = ApiError::builder(StatusCode::BAD_REQUEST).title(title);
+    if let Some((line, column)) = position {
+        builder = builder.field("line", line).field("column", column);
+    }
+    builder.finish()
+}
+
 impl From<std::convert::Infallible> for ApiError {
     fn from(error: std::convert::Infallible) -> Self {
         match error {}
@@ -626,6 +907,8 @@ impl From<ApiError> for actix_web::HttpResponse {
 #[cfg(feature = "actix-web")]
 impl actix_web::error::ResponseError for ApiError {
     fn error_response(&self) -> actix_web::HttpResponse {
+        report_server_error(self);
+
         let json = self.to_http_api_problem().json_bytes();
         let actix_status = actix_web::http::StatusCode::from_u16(self.status.as_u16())
             .unwrap_or(actix_web::http::StatusCode::INTERNAL_SERVER_ERROR);
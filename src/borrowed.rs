@@ -0,0 +1,336 @@
+//! A borrowed problem for hot paths.
+//!
+//! [HttpApiProblem] owns all of its members and stores additional fields as
+//! [serde_json::Value]s. [HttpApiProblemRef] borrows everything instead and
+//! takes additional fields as pre-serialized [RawValue]s so that it can be
+//! serialized without any intermediate allocations.
+//!
+//! Since it only consists of references it can also be built in a `const`
+//! context. Problems for common status codes are available as associated
+//! constants.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! static OUT_OF_CREDIT: HttpApiProblemRef = HttpApiProblemRef::new(StatusCode::FORBIDDEN)
+//!     .title("You do not have enough credit.")
+//!     .type_url("https://example.com/probs/out-of-credit");
+//!
+//! assert_eq!(
+//!     OUT_OF_CREDIT.json_string(),
+//!     r#"{"type":"https://example.com/probs/out-of-credit","status":403,"title":"You do not have enough credit."}"#
+//! );
+//!
+//! assert_eq!(
+//!     HttpApiProblemRef::NOT_FOUND.to_http_api_problem(),
+//!     HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+//! );
+//! ```
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use serde_json::value::RawValue;
+
+use super::*;
+
+/// A borrowed version of an [HttpApiProblem].
+///
+/// Additional fields are given as pairs of keys and pre-serialized
+/// JSON values. Fields with the name of a standard member are skipped
+/// like with [HttpApiProblem::set_value].
+#[derive(Debug, Clone, Copy)]
+pub struct HttpApiProblemRef<'a> {
+    /// A URI reference that identifies the problem type.
+    pub type_url: Option<&'a str>,
+    /// The HTTP status code generated by the origin server for this
+    /// occurrence of the problem.
+    pub status: Option<StatusCode>,
+    /// A short, human-readable summary of the problem type.
+    pub title: Option<&'a str>,
+    /// A human-readable explanation specific to this
+    /// occurrence of the problem.
+    pub detail: Option<&'a str>,
+    /// A URI reference that identifies the specific
+    /// occurrence of the problem.
+    pub instance: Option<&'a str>,
+    /// Additional fields which get serialized into the JSON
+    /// on top level.
+    pub additional_fields: &'a [(&'a str, &'a RawValue)],
+}
+
+impl HttpApiProblemRef<'static> {
+    pub const BAD_REQUEST: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::BAD_REQUEST,
+        "Bad Request",
+        "https://httpstatuses.com/400",
+    );
+    pub const UNAUTHORIZED: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::UNAUTHORIZED,
+        "Unauthorized",
+        "https://httpstatuses.com/401",
+    );
+    pub const FORBIDDEN: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::FORBIDDEN,
+        "Forbidden",
+        "https://httpstatuses.com/403",
+    );
+    pub const NOT_FOUND: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::NOT_FOUND,
+        "Not Found",
+        "https://httpstatuses.com/404",
+    );
+    pub const CONFLICT: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::CONFLICT,
+        "Conflict",
+        "https://httpstatuses.com/409",
+    );
+    pub const UNPROCESSABLE_ENTITY: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::UNPROCESSABLE_ENTITY,
+        "Unprocessable Entity",
+        "https://httpstatuses.com/422",
+    );
+    pub const TOO_MANY_REQUESTS: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::TOO_MANY_REQUESTS,
+        "Too Many Requests",
+        "https://httpstatuses.com/429",
+    );
+    pub const INTERNAL_SERVER_ERROR: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::INTERNAL_SERVER_ERROR,
+        "Internal Server Error",
+        "https://httpstatuses.com/500",
+    );
+    pub const BAD_GATEWAY: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::BAD_GATEWAY,
+        "Bad Gateway",
+        "https://httpstatuses.com/502",
+    );
+    pub const SERVICE_UNAVAILABLE: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::SERVICE_UNAVAILABLE,
+        "Service Unavailable",
+        "https://httpstatuses.com/503",
+    );
+    pub const GATEWAY_TIMEOUT: HttpApiProblemRef<'static> = Self::for_status(
+        StatusCode::GATEWAY_TIMEOUT,
+        "Gateway Timeout",
+        "https://httpstatuses.com/504",
+    );
+
+    const fn for_status(status: StatusCode, title: &'static str, type_url: &'static str) -> Self {
+        Self::new(status).title(title).type_url(type_url)
+    }
+}
+
+impl<'a> HttpApiProblemRef<'a> {
+    /// Creates a new instance with the given [StatusCode].
+    pub const fn new(status: StatusCode) -> Self {
+        Self::empty().status(status)
+    }
+
+    /// Creates a new instance without any field set.
+    pub const fn empty() -> Self {
+        Self {
+            type_url: None,
+            status: None,
+            title: None,
+            detail: None,
+            instance: None,
+            additional_fields: &[],
+        }
+    }
+
+    /// Sets the `status`
+    pub const fn status(mut self, status: StatusCode) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    /// Sets the `type_url`
+    pub const fn type_url(mut self, type_url: &'a str) -> Self {
+        self.type_url = Some(type_url);
+        self
+    }
+
+    /// Sets the `title`
+    pub const fn title(mut self, title: &'a str) -> Self {
+        self.title = Some(title);
+        self
+    }
+
+    /// Sets the `detail`
+    pub const fn detail(mut self, detail: &'a str) -> Self {
+        self.detail = Some(detail);
+        self
+    }
+
+    /// Sets the `instance`
+    pub const fn instance(mut self, instance: &'a str) -> Self {
+        self.instance = Some(instance);
+        self
+    }
+
+    /// Sets the additional fields
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    /// use serde_json::value::RawValue;
+    ///
+    /// let balance = RawValue::from_string("30".to_string()).unwrap();
+    /// let fields = [("balance", &*balance)];
+    ///
+    /// let p = HttpApiProblemRef::new(StatusCode::FORBIDDEN).additional_fields(&fields);
+    ///
+    /// assert_eq!(p.json_string(), r#"{"status":403,"balance":30}"#);
+    /// ```
+    pub const fn additional_fields(mut self, fields: &'a [(&'a str, &'a RawValue)]) -> Self {
+        self.additional_fields = fields;
+        self
+    }
+
+    /// The additional fields without those with the name of a standard member
+    fn extension_fields(&self) -> impl Iterator<Item = (&'a str, &'a RawValue)> {
+        self.additional_fields
+            .iter()
+            .copied()
+            .filter(|(key, _)| !conformance::is_reserved_field_name(key))
+    }
+
+    /// Serialize to a JSON `Vec<u8>`
+    pub fn json_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).unwrap()
+    }
+
+    /// Serialize to a JSON `String`
+    ///
+    /// Other than [HttpApiProblem::json_string] the output is not
+    /// pretty printed.
+    pub fn json_string(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Creates an owned [HttpApiProblem] from this.
+    pub fn to_http_api_problem(&self) -> HttpApiProblem {
+        let mut problem = HttpApiProblem::empty();
+        problem.type_url = self.type_url.map(ToOwned::to_owned);
+        problem.status = self.status;
+        problem.title = self.title.map(ToOwned::to_owned);
+        problem.detail = self.detail.map(ToOwned::to_owned);
+        problem.instance = self.instance.map(ToOwned::to_owned);
+
+        for (key, value) in self.extension_fields() {
+            if let Ok(value) = serde_json::from_str::<Value>(value.get()) {
+                problem.set_value(key, &value);
+            }
+        }
+
+        problem
+    }
+
+    /// Creates a [hyper] response.
+    ///
+    /// If status is `None` `500 - Internal Server Error` is the
+    /// default.
+    ///
+    /// Requires the `hyper` feature
+    #[cfg(feature = "hyper")]
+    pub fn to_hyper_response(&self) -> hyper::Response<String> {
+        use hyper::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+        use hyper::*;
+
+        let json = self.json_string();
+        let length = json.len() as u64;
+
+        let (mut parts, body) = Response::new(json).into_parts();
+
+        parts.headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_MEDIA_TYPE),
+        );
+        parts
+            .headers
+            .insert(CONTENT_LENGTH, HeaderValue::from(length));
+        parts.status = self.status.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        Response::from_parts(parts, body)
+    }
+
+    /// Creates an axum [Response](axum_core::response::Response).
+    ///
+    /// If status is `None` `500 - Internal Server Error` is the
+    /// default.
+    ///
+    /// Requires the `axum` feature
+    #[cfg(feature = "axum")]
+    pub fn to_axum_response(&self) -> axum_core::response::Response {
+        use axum_core::response::IntoResponse;
+        use http::header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE};
+
+        let json = self.json_bytes();
+        let length = json.len() as u64;
+
+        let status = self.status.unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+        let mut response = (status, json).into_response();
+
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(PROBLEM_JSON_MEDIA_TYPE),
+        );
+        response
+            .headers_mut()
+            .insert(CONTENT_LENGTH, HeaderValue::from(length));
+
+        response
+    }
+}
+
+impl Serialize for HttpApiProblemRef<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = serializer.serialize_map(None)?;
+
+        if let Some(type_url) = self.type_url {
+            map.serialize_entry("type", type_url)?;
+        }
+        if let Some(status) = self.status {
+            map.serialize_entry("status", &status.as_u16())?;
+        }
+        if let Some(title) = self.title {
+            map.serialize_entry("title", title)?;
+        }
+        if let Some(detail) = self.detail {
+            map.serialize_entry("detail", detail)?;
+        }
+        if let Some(instance) = self.instance {
+            map.serialize_entry("instance", instance)?;
+        }
+        for (key, value) in self.extension_fields() {
+            map.serialize_entry(key, value)?;
+        }
+
+        map.end()
+    }
+}
+
+impl From<HttpApiProblemRef<'_>> for HttpApiProblem {
+    fn from(problem: HttpApiProblemRef<'_>) -> Self {
+        problem.to_http_api_problem()
+    }
+}
+
+#[cfg(feature = "hyper")]
+impl From<HttpApiProblemRef<'_>> for hyper::Response<String> {
+    fn from(problem: HttpApiProblemRef<'_>) -> hyper::Response<String> {
+        problem.to_hyper_response()
+    }
+}
+
+#[cfg(feature = "axum")]
+impl axum_core::response::IntoResponse for HttpApiProblemRef<'_> {
+    fn into_response(self) -> axum_core::response::Response {
+        self.to_axum_response()
+    }
+}
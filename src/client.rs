@@ -0,0 +1,432 @@
+//! Extraction of problems from HTTP responses received by clients.
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+use http::header::{HeaderMap, CONTENT_TYPE};
+
+use super::*;
+
+/// The maximum number of bytes of a body collected by the
+/// `error_for_problem` methods of the client integrations
+pub const DEFAULT_PROBLEM_BODY_LIMIT: usize = 64 * 1024;
+
+/// A problem received in a response together with the
+/// status and the headers of the response.
+#[derive(Debug, Clone)]
+pub struct ProblemResponse {
+    /// The status of the response
+    pub status: StatusCode,
+    /// The headers of the response
+    pub headers: HeaderMap,
+    /// The problem extracted from the response
+    ///
+    /// See [HttpApiProblem::from_http_response]
+    pub problem: HttpApiProblem,
+}
+
+impl ProblemResponse {
+    /// Extracts the problem from the parts of a response
+    pub fn from_parts(status: StatusCode, headers: HeaderMap, body: &[u8]) -> Self {
+        let problem = HttpApiProblem::from_response_parts(status, &headers, body);
+        Self {
+            status,
+            headers,
+            problem,
+        }
+    }
+}
+
+/// An error of an HTTP client which checks responses for problems.
+#[derive(Debug)]
+pub enum ClientProblemError {
+    /// The server responded with a status other than `2xx`
+    Problem(Box<ProblemResponse>),
+    /// The response could not be received completely
+    Transport(Box<dyn Error + Send + Sync + 'static>),
+}
+
+impl ClientProblemError {
+    /// Returns the problem if the server responded with one
+    pub fn problem(&self) -> Option<&HttpApiProblem> {
+        match self {
+            ClientProblemError::Problem(response) => Some(&response.problem),
+            ClientProblemError::Transport(_) => None,
+        }
+    }
+
+    /// Returns the status of the response if one was received
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            ClientProblemError::Problem(response) => Some(response.status),
+            ClientProblemError::Transport(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ClientProblemError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientProblemError::Problem(response) => write!(f, "{}", response.problem),
+            ClientProblemError::Transport(err) => write!(f, "transport error: {}", err),
+        }
+    }
+}
+
+impl Error for ClientProblemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ClientProblemError::Problem(response) => Some(&response.problem),
+            ClientProblemError::Transport(err) => Some(&**err),
+        }
+    }
+}
+
+impl From<ClientProblemError> for HttpApiProblem {
+    /// Returns the received problem or a `502 - Bad Gateway` problem
+    /// for transport errors.
+    fn from(err: ClientProblemError) -> Self {
+        match err {
+            ClientProblemError::Problem(response) => response.problem,
+            ClientProblemError::Transport(err) => {
+                HttpApiProblem::with_title(StatusCode::BAD_GATEWAY).detail(err.to_string())
+            }
+        }
+    }
+}
+
+/// The media type of a response body
+enum BodyKind {
+    ProblemJson,
+    ProblemXml,
+    Other,
+}
+
+impl BodyKind {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let content_type = match headers
+            .get(CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        {
+            Some(content_type) => content_type,
+            None => return BodyKind::Other,
+        };
+
+        let media_type = content_type.split(';').next().unwrap_or_default().trim();
+
+        if media_type.eq_ignore_ascii_case(PROBLEM_JSON_MEDIA_TYPE) {
+            BodyKind::ProblemJson
+        } else if media_type.eq_ignore_ascii_case(PROBLEM_XML_MEDIA_TYPE) {
+            BodyKind::ProblemXml
+        } else {
+            BodyKind::Other
+        }
+    }
+}
+
+impl HttpApiProblem {
+    /// Extracts a problem from an HTTP response.
+    ///
+    /// If the `Content-Type` of the response is `application/problem+json`
+    /// (parameters like `charset` are allowed) the body is parsed
+    /// [leniently](HttpApiProblem::parse_lenient). If the body does not
+    /// contain a `status` the status of the response is used.
+    ///
+    /// Bodies with the `Content-Type` `application/problem+xml` are parsed
+    /// according to [RFC 7807, Appendix A](https://tools.ietf.org/html/rfc7807#appendix-A).
+    /// Only additional fields with text content are taken from them.
+    /// Documents with a document type declaration containing an internal
+    /// subset are not supported and neither are documents which are not
+    /// well formed. Namespaces are not checked.
+    ///
+    /// In all other cases a problem with the status of the response and
+    /// a title derived from it is returned.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let response = http::Response::builder()
+    ///     .status(StatusCode::FORBIDDEN)
+    ///     .header("Content-Type", "application/problem+json; charset=utf-8")
+    ///     .body(r#"{"title": "You do not have enough credit."}"#)
+    ///     .unwrap();
+    ///
+    /// let p = HttpApiProblem::from_http_response(&response);
+    ///
+    /// assert_eq!(Some(StatusCode::FORBIDDEN), p.status);
+    /// assert_eq!(Some("You do not have enough credit."), p.title.as_deref());
+    ///
+    /// let response = http::Response::builder()
+    ///     .status(StatusCode::BAD_GATEWAY)
+    ///     .header("Content-Type", "text/html")
+    ///     .body("<h1>Bad Gateway</h1>")
+    ///     .unwrap();
+    ///
+    /// let p = HttpApiProblem::from_http_response(&response);
+    ///
+    /// assert_eq!(p, HttpApiProblem::with_title(StatusCode::BAD_GATEWAY));
+    /// ```
+    pub fn from_http_response<B: AsRef<[u8]>>(response: &http::Response<B>) -> Self {
+        Self::from_response_parts(
+            response.status(),
+            response.headers(),
+            response.body().as_ref(),
+        )
+    }
+
+    /// Extracts a problem from the parts of an HTTP response.
+    ///
+    /// See [HttpApiProblem::from_http_response]
+    pub fn from_response_parts(status: StatusCode, headers: &HeaderMap, body: &[u8]) -> Self {
+        let parsed = match BodyKind::from_headers(headers) {
+            BodyKind::ProblemJson => HttpApiProblem::parse_lenient(body).ok(),
+            BodyKind::ProblemXml => parse_problem_xml(body),
+            BodyKind::Other => None,
+        };
+
+        match parsed {
+            Some(mut problem) => {
+                if problem.status.is_none() {
+                    problem.status = Some(status);
+                }
+                problem
+            }
+            None => HttpApiProblem::with_title(status),
+        }
+    }
+}
+
+/// A parser for the XML format of RFC 7807
+///
+/// Supported is the subset of XML used by the format: a prolog,
+/// comments, processing instructions, CDATA sections and a document
+/// type declaration without an internal subset. Namespaces are not
+/// checked, elements are identified by their local names.
+///
+/// Only direct children of the `problem` element with text content are
+/// taken into account. Children with child elements are skipped.
+///
+/// Returns `None` if the root element is not `problem` or the document
+/// is not well formed or uses unsupported features.
+fn parse_problem_xml(body: &[u8]) -> Option<HttpApiProblem> {
+    let xml = std::str::from_utf8(body).ok()?;
+    let mut tokens = XmlTokens { rest: xml };
+
+    let root = loop {
+        match tokens.next()? {
+            XmlToken::Text(text) if text.trim().is_empty() => continue,
+            XmlToken::Start { name, self_closing } => break (name, self_closing),
+            _ => return None,
+        }
+    };
+
+    if local_name(root.0) != "problem" {
+        return None;
+    }
+
+    let mut problem = HttpApiProblem::empty();
+    if root.1 {
+        return Some(problem);
+    }
+
+    loop {
+        let (name, self_closing) = match tokens.next()? {
+            XmlToken::Text(_) => continue,
+            XmlToken::End(name) if name == root.0 => break,
+            XmlToken::End(_) => return None,
+            XmlToken::Start { name, self_closing } => (name, self_closing),
+        };
+
+        let text = if self_closing {
+            Some(String::new())
+        } else {
+            element_text(&mut tokens, name)?
+        };
+
+        if let Some(text) = text {
+            match local_name(name) {
+                "type" => problem.type_url = Some(text),
+                "title" => problem.title = Some(text),
+                "detail" => problem.detail = Some(text),
+                "instance" => problem.instance = Some(text),
+                "status" => {
+                    problem.status = text
+                        .trim()
+                        .parse::<u16>()
+                        .ok()
+                        .and_then(|s| s.try_into().ok())
+                }
+                local_name => problem.set_value(local_name.to_string(), &text),
+            }
+        }
+    }
+
+    Some(problem)
+}
+
+/// Consumes the content of an element up to its end tag
+///
+/// Returns `Some(None)` if the element has child elements.
+fn element_text(tokens: &mut XmlTokens<'_>, name: &str) -> Option<Option<String>> {
+    let mut text = String::new();
+    let mut has_children = false;
+    let mut open = Vec::new();
+
+    loop {
+        match tokens.next()? {
+            XmlToken::Text(t) => text.push_str(&t),
+            XmlToken::Start {
+                name: child,
+                self_closing,
+            } => {
+                has_children = true;
+                if !self_closing {
+                    open.push(child);
+                }
+            }
+            XmlToken::End(end) => match open.pop() {
+                Some(child) if child == end => {}
+                Some(_) => return None,
+                None if end == name => break,
+                None => return None,
+            },
+        }
+    }
+
+    Some((!has_children).then_some(text))
+}
+
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+enum XmlToken<'a> {
+    Start { name: &'a str, self_closing: bool },
+    End(&'a str),
+    Text(Cow<'a, str>),
+}
+
+/// Splits an XML document into tags and text
+///
+/// Comments, processing instructions and document type declarations
+/// are skipped. Ends if the document is not well formed.
+struct XmlTokens<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Iterator for XmlTokens<'a> {
+    type Item = XmlToken<'a>;
+
+    fn next(&mut self) -> Option<XmlToken<'a>> {
+        let token = self.next_token();
+        if token.is_none() {
+            self.rest = "";
+        }
+        token
+    }
+}
+
+impl<'a> XmlTokens<'a> {
+    fn next_token(&mut self) -> Option<XmlToken<'a>> {
+        loop {
+            let rest = self.rest;
+            if rest.is_empty() {
+                return None;
+            }
+
+            if let Some(after) = rest.strip_prefix("<?") {
+                self.rest = &after[after.find("?>")? + 2..];
+            } else if let Some(after) = rest.strip_prefix("<!--") {
+                self.rest = &after[after.find("-->")? + 3..];
+            } else if let Some(after) = rest.strip_prefix("<![CDATA[") {
+                let end = after.find("]]>")?;
+                self.rest = &after[end + 3..];
+                return Some(XmlToken::Text(Cow::Borrowed(&after[..end])));
+            } else if let Some(after) = rest.strip_prefix("<!") {
+                let end = after.find('>')?;
+                // Internal subsets may declare entities which are not supported
+                if after[..end].contains('[') {
+                    return None;
+                }
+                self.rest = &after[end + 1..];
+            } else if let Some(after) = rest.strip_prefix("</") {
+                let end = after.find('>')?;
+                self.rest = &after[end + 1..];
+                return Some(XmlToken::End(after[..end].trim_end()));
+            } else if let Some(after) = rest.strip_prefix('<') {
+                let end = tag_end(after)?;
+                let tag = &after[..end];
+                self.rest = &after[end + 1..];
+                let self_closing = tag.ends_with('/');
+                let name = tag
+                    .trim_end_matches('/')
+                    .split(|c: char| c.is_whitespace())
+                    .next()
+                    .filter(|name| !name.is_empty())?;
+                return Some(XmlToken::Start { name, self_closing });
+            } else {
+                let end = rest.find('<').unwrap_or(rest.len());
+                self.rest = &rest[end..];
+                return Some(XmlToken::Text(Cow::Owned(unescape_xml(&rest[..end]))));
+            }
+        }
+    }
+}
+
+/// Finds the `>` closing a start tag while skipping quoted attribute values
+fn tag_end(tag: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (None, '>') => return Some(idx),
+            _ => {}
+        }
+    }
+    None
+}
+
+fn unescape_xml(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        unescaped.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let entity = rest.find(';').map(|end| (&rest[1..end], end));
+        let replacement = entity.and_then(|(entity, end)| {
+            let c = match entity {
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "amp" => Some('&'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => entity
+                    .strip_prefix("#x")
+                    .map(|hex| u32::from_str_radix(hex, 16))
+                    .or_else(|| entity.strip_prefix('#').map(str::parse))
+                    .and_then(Result::ok)
+                    .and_then(char::from_u32),
+            };
+            c.map(|c| (c, end))
+        });
+
+        match replacement {
+            Some((c, end)) => {
+                unescaped.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                unescaped.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    unescaped.push_str(rest);
+    unescaped
+}
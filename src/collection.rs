@@ -0,0 +1,304 @@
+//! Several problems reported in a single response.
+//!
+//! [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457#section-3) recommends
+//! to report multiple problems of the same kind as one problem whose
+//! extension member lists the individual occurrences. A [ProblemCollection]
+//! aggregates problems into a problem with the member `errors` and
+//! [HttpApiProblem::split_errors] gets them back on the client side.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let problem = ProblemCollection::new()
+//!     .title("Some items could not be imported")
+//!     .problem(HttpApiProblem::new(StatusCode::CONFLICT).detail("item 1 exists"))
+//!     .problem(HttpApiProblem::new(StatusCode::BAD_REQUEST).detail("item 2 has no name"))
+//!     .into_http_api_problem()
+//!     .unwrap();
+//!
+//! assert_eq!(problem.status, Some(StatusCode::BAD_REQUEST));
+//!
+//! let (_, errors) = problem.split_errors();
+//! assert_eq!(errors.len(), 2);
+//! assert_eq!(errors[0].detail.as_deref(), Some("item 1 exists"));
+//! ```
+use super::*;
+
+/// The field containing the individual problems
+pub const ERRORS_FIELD: &str = "errors";
+
+/// The field containing the number of problems if not all of them are listed
+pub const TOTAL_ERRORS_FIELD: &str = "total_errors";
+
+/// How the status of a [ProblemCollection] is determined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CollectionStatus {
+    /// The status of the most severe problem
+    ///
+    /// Server errors are more severe than client errors. If the problems of
+    /// the most severe class have different statuses the generic status of
+    /// the class (`400` or `500`) is used. A collection without any status
+    /// gets a `500 - Internal Server Error`.
+    #[default]
+    MostSevere,
+    /// Always the given status, e.g. `207 - Multi-Status`
+    /// or `422 - Unprocessable Entity`
+    Fixed(StatusCode),
+}
+
+/// Aggregates several problems into one problem with an `errors` array
+#[derive(Debug, Clone, Default)]
+pub struct ProblemCollection {
+    /// The title of the aggregated problem
+    pub title: Option<String>,
+    /// The type of the aggregated problem
+    pub type_url: Option<String>,
+    /// The detail of the aggregated problem
+    pub detail: Option<String>,
+    /// How the status of the aggregated problem is determined
+    pub status: CollectionStatus,
+    /// The maximum number of problems listed in `errors`
+    ///
+    /// If there are more problems the total number is put
+    /// into the field `total_errors`.
+    pub max_items: Option<usize>,
+    items: Vec<HttpApiProblem>,
+}
+
+impl ProblemCollection {
+    /// Creates an empty collection
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the title of the aggregated problem
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the type of the aggregated problem
+    pub fn type_url<T: Into<String>>(mut self, type_url: T) -> Self {
+        self.type_url = Some(type_url.into());
+        self
+    }
+
+    /// Sets the detail of the aggregated problem
+    pub fn detail<T: Into<String>>(mut self, detail: T) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Sets how the status of the aggregated problem is determined
+    pub fn status(mut self, status: CollectionStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Limits the number of problems listed in `errors`
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    /// Adds a problem
+    pub fn problem<P: Into<HttpApiProblem>>(mut self, problem: P) -> Self {
+        self.push(problem);
+        self
+    }
+
+    /// Adds a problem
+    pub fn push<P: Into<HttpApiProblem>>(&mut self, problem: P) {
+        self.items.push(problem.into());
+    }
+
+    /// Returns the number of problems in this collection
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns `true` if this collection contains no problems
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Returns the problems of this collection
+    pub fn problems(&self) -> &[HttpApiProblem] {
+        &self.items
+    }
+
+    /// Returns the status of the aggregated problem
+    pub fn overall_status(&self) -> StatusCode {
+        match self.status {
+            CollectionStatus::Fixed(status) => status,
+            CollectionStatus::MostSevere => most_severe(&self.items),
+        }
+    }
+
+    /// Creates the aggregated problem
+    ///
+    /// A missing title is derived from the status. Returns `None` if
+    /// the collection is empty since there is no problem to report.
+    pub fn into_http_api_problem(mut self) -> Option<HttpApiProblem> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let status = self.overall_status();
+        let (errors, total) = self.take_errors();
+
+        let mut problem = HttpApiProblem::new(status);
+        problem.title = self
+            .title
+            .or_else(|| status.canonical_reason().map(str::to_owned));
+        problem.type_url = self.type_url;
+        problem.detail = self.detail;
+        problem.set_value(ERRORS_FIELD, &errors);
+        if let Some(total) = total {
+            problem.set_value(TOTAL_ERRORS_FIELD, &total);
+        }
+
+        Some(problem)
+    }
+
+    /// Creates an [ApiError] for the aggregated problem
+    ///
+    /// Returns `None` if the collection is empty.
+    ///
+    /// Requires the `api-error` feature
+    #[cfg(feature = "api-error")]
+    pub fn into_api_error(mut self) -> Option<ApiError> {
+        if self.is_empty() {
+            return None;
+        }
+
+        let status = self.overall_status();
+        let (errors, total) = self.take_errors();
+
+        let mut builder = ApiError::builder(status).field(ERRORS_FIELD, errors);
+        builder.title = self.title;
+        builder.type_url = self.type_url;
+        builder.message = self.detail;
+        if let Some(total) = total {
+            builder = builder.field(TOTAL_ERRORS_FIELD, total);
+        }
+
+        Some(builder.finish())
+    }
+
+    /// Takes the problems to be listed and the total number
+    /// if the problems had to be truncated
+    fn take_errors(&mut self) -> (Vec<HttpApiProblem>, Option<usize>) {
+        let mut items = std::mem::take(&mut self.items);
+        match self.max_items {
+            Some(max_items) if items.len() > max_items => {
+                let total = items.len();
+                items.truncate(max_items);
+                (items, Some(total))
+            }
+            _ => (items, None),
+        }
+    }
+}
+
+impl<P: Into<HttpApiProblem>> Extend<P> for ProblemCollection {
+    fn extend<I: IntoIterator<Item = P>>(&mut self, iter: I) {
+        self.items.extend(iter.into_iter().map(Into::into));
+    }
+}
+
+impl<P: Into<HttpApiProblem>> FromIterator<P> for ProblemCollection {
+    fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
+        let mut collection = Self::new();
+        collection.extend(iter);
+        collection
+    }
+}
+
+fn most_severe(items: &[HttpApiProblem]) -> StatusCode {
+    let statuses = || items.iter().filter_map(|item| item.status);
+
+    let (server_error, generic) = if statuses().any(|s| s.is_server_error()) {
+        (true, StatusCode::INTERNAL_SERVER_ERROR)
+    } else if statuses().any(|s| s.is_client_error()) {
+        (false, StatusCode::BAD_REQUEST)
+    } else {
+        return StatusCode::INTERNAL_SERVER_ERROR;
+    };
+
+    let mut in_class = statuses().filter(|s| {
+        if server_error {
+            s.is_server_error()
+        } else {
+            s.is_client_error()
+        }
+    });
+    let first = in_class.next().unwrap_or(generic);
+    if in_class.all(|s| s == first) {
+        first
+    } else {
+        generic
+    }
+}
+
+impl HttpApiProblem {
+    /// Splits a problem created by a [ProblemCollection] into the
+    /// aggregated problem without `errors` and the individual problems.
+    ///
+    /// Entries of `errors` which are not objects are skipped. The others
+    /// are parsed [leniently](HttpApiProblem::parse_lenient). If `errors`
+    /// is not an array the problem is returned as it is.
+    pub fn split_errors(mut self) -> (HttpApiProblem, Vec<HttpApiProblem>) {
+        if !self.json_value(ERRORS_FIELD).is_some_and(Value::is_array) {
+            return (self, Vec::new());
+        }
+
+        let errors = match self.additional_fields.remove(ERRORS_FIELD) {
+            Some(Value::Array(errors)) => errors
+                .into_iter()
+                .filter_map(|error| match error {
+                    Value::Object(members) => Some(parse::parse_members(members, false).0),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        (self, errors)
+    }
+
+    /// Returns the total number of problems in `errors`
+    ///
+    /// This is the value of `total_errors` if the list was truncated
+    /// or the length of `errors` otherwise.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let problem = ProblemCollection::new()
+    ///     .max_items(2)
+    ///     .status(CollectionStatus::Fixed(StatusCode::UNPROCESSABLE_ENTITY))
+    ///     .problem(HttpApiProblem::new(StatusCode::BAD_REQUEST))
+    ///     .problem(HttpApiProblem::new(StatusCode::BAD_REQUEST))
+    ///     .problem(HttpApiProblem::new(StatusCode::BAD_REQUEST))
+    ///     .into_http_api_problem()
+    ///     .unwrap();
+    ///
+    /// assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+    /// assert_eq!(problem.total_errors(), Some(3));
+    /// assert_eq!(problem.split_errors().1.len(), 2);
+    /// ```
+    pub fn total_errors(&self) -> Option<usize> {
+        if let Some(total) = self.json_value(TOTAL_ERRORS_FIELD).and_then(Value::as_u64) {
+            return Some(total as usize);
+        }
+
+        self.json_value(ERRORS_FIELD)
+            .and_then(Value::as_array)
+            .map(Vec::len)
+    }
+}
@@ -0,0 +1,233 @@
+//! Checks of problems against the rules of RFC 7807 and RFC 9457.
+//!
+//! Problems which violate the rules can still be serialized but clients
+//! might not be able to process them. The checks are meant to be run in
+//! tests or debug builds.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let p = HttpApiProblem::new(StatusCode::OK)
+//!     .type_url("https://example.com/probs/out of credit")
+//!     .value("id", &42);
+//!
+//! assert_eq!(
+//!     p.validate(),
+//!     vec![
+//!         Violation::InvalidTypeUri("https://example.com/probs/out of credit".into()),
+//!         Violation::StatusNotAnError(StatusCode::OK),
+//!         Violation::UnconventionalFieldName("id".into()),
+//!     ]
+//! );
+//! ```
+use std::fmt;
+
+use super::*;
+
+/// A violation of the rules for problem objects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// `type` is not a valid URI reference
+    InvalidTypeUri(String),
+    /// `instance` is not a valid URI reference
+    InvalidInstanceUri(String),
+    /// `status` is neither a client error (`4xx`) nor a server error (`5xx`)
+    StatusNotAnError(StatusCode),
+    /// `status` differs from the status of the response carrying the problem
+    StatusMismatch {
+        problem: StatusCode,
+        response: StatusCode,
+    },
+    /// An additional field uses the name of a standard member or
+    /// a name reserved by this crate
+    ReservedFieldName(String),
+    /// The name of an additional field does not follow the recommendation
+    /// of RFC 9457
+    ///
+    /// Names should start with a letter, consist of letters, digits and `_`
+    /// and be at least three characters long.
+    UnconventionalFieldName(String),
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::InvalidTypeUri(uri) => {
+                write!(f, "'type' is not a valid URI reference: {}", uri)
+            }
+            Violation::InvalidInstanceUri(uri) => {
+                write!(f, "'instance' is not a valid URI reference: {}", uri)
+            }
+            Violation::StatusNotAnError(status) => {
+                write!(f, "'status' is not an error status: {}", status)
+            }
+            Violation::StatusMismatch { problem, response } => write!(
+                f,
+                "'status' is {} but the response status is {}",
+                problem, response
+            ),
+            Violation::ReservedFieldName(name) => {
+                write!(f, "'{}' is a reserved field name", name)
+            }
+            Violation::UnconventionalFieldName(name) => {
+                write!(f, "'{}' is not a recommended field name", name)
+            }
+        }
+    }
+}
+
+impl HttpApiProblem {
+    /// Checks this problem against the rules of RFC 7807 and RFC 9457
+    /// and returns all violations.
+    ///
+    /// The following is checked:
+    ///
+    /// * `type` and `instance` must be URI references
+    /// * `status` must be a client error or a server error
+    /// * names of additional fields must not be reserved and should follow
+    ///   the recommendation of RFC 9457
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+
+        if let Some(type_url) = self.type_url.as_deref() {
+            if !is_uri_reference(type_url) {
+                violations.push(Violation::InvalidTypeUri(type_url.to_owned()));
+            }
+        }
+
+        if let Some(instance) = self.instance.as_deref() {
+            if !is_uri_reference(instance) {
+                violations.push(Violation::InvalidInstanceUri(instance.to_owned()));
+            }
+        }
+
+        if let Some(status) = self.status {
+            if !status.is_client_error() && !status.is_server_error() {
+                violations.push(Violation::StatusNotAnError(status));
+            }
+        }
+
+        let mut names: Vec<&String> = self.additional_fields.keys().collect();
+        names.sort();
+        for name in names {
+            if is_reserved_field_name(name) {
+                violations.push(Violation::ReservedFieldName(name.clone()));
+            } else if !is_recommended_field_name(name) {
+                violations.push(Violation::UnconventionalFieldName(name.clone()));
+            }
+        }
+
+        violations
+    }
+
+    /// Like [HttpApiProblem::validate] but additionally checks that `status`
+    /// matches the status of the response carrying this problem.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let p = HttpApiProblem::new(StatusCode::NOT_FOUND);
+    ///
+    /// assert!(p.validate_for_response(StatusCode::NOT_FOUND).is_empty());
+    /// assert_eq!(
+    ///     p.validate_for_response(StatusCode::GONE),
+    ///     vec![Violation::StatusMismatch {
+    ///         problem: StatusCode::NOT_FOUND,
+    ///         response: StatusCode::GONE
+    ///     }]
+    /// );
+    /// ```
+    pub fn validate_for_response(&self, response_status: StatusCode) -> Vec<Violation> {
+        let mut violations = self.validate();
+
+        if let Some(status) = self.status {
+            if status != response_status {
+                violations.push(Violation::StatusMismatch {
+                    problem: status,
+                    response: response_status,
+                });
+            }
+        }
+
+        violations
+    }
+}
+
+pub(crate) fn is_reserved_field_name(name: &str) -> bool {
+    matches!(
+        name,
+        "type" | "status" | "title" | "detail" | "instance" | "additional_fields"
+    )
+}
+
+fn is_recommended_field_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let starts_with_letter = chars.next().is_some_and(|c| c.is_ascii_alphabetic());
+
+    starts_with_letter && chars.all(|c| c.is_ascii_alphanumeric() || c == '_') && name.len() >= 3
+}
+
+/// Checks whether `s` is a URI reference as defined in
+/// [RFC 3986, Section 4.1](https://tools.ietf.org/html/rfc3986#section-4.1)
+///
+/// This checks the characters, percent encodings, the scheme and
+/// the position of the fragment. It does not check the authority in detail.
+pub(crate) fn is_uri_reference(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    let mut idx = 0;
+    while idx < bytes.len() {
+        match bytes[idx] {
+            b'%' => {
+                let is_hex_pair = bytes.len() > idx + 2
+                    && bytes[idx + 1].is_ascii_hexdigit()
+                    && bytes[idx + 2].is_ascii_hexdigit();
+                if !is_hex_pair {
+                    return false;
+                }
+                idx += 3;
+                continue;
+            }
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => (),
+            b'-' | b'.' | b'_' | b'~' => (),
+            b':' | b'/' | b'?' | b'#' | b'[' | b']' | b'@' => (),
+            b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'=' => (),
+            _ => return false,
+        }
+        idx += 1;
+    }
+
+    if s.matches('#').count() > 1 {
+        return false;
+    }
+
+    // A colon before the first '/', '?' or '#' terminates a scheme.
+    // Relative references can not have a colon in their first segment.
+    let first_part_end = s.find(['/', '?', '#']).unwrap_or(s.len());
+    if let Some(colon) = s[..first_part_end].find(':') {
+        if !is_scheme(&s[..colon]) {
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Checks whether a URI reference starts with a scheme and
+/// therefore is an absolute URI
+#[cfg(feature = "url")]
+pub(crate) fn has_scheme(s: &str) -> bool {
+    let first_part_end = s.find(['/', '?', '#']).unwrap_or(s.len());
+    s[..first_part_end]
+        .find(':')
+        .is_some_and(|colon| is_scheme(&s[..colon]))
+}
+
+fn is_scheme(s: &str) -> bool {
+    let mut chars = s.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+}
@@ -0,0 +1,30 @@
+//! Conversion of `eyre::Report` into [ApiError].
+//!
+//! The whole error including its context becomes the source of the
+//! [ApiError]. The status is taken from the outermost [StatusHint] attached
+//! with [EyreStatusExt::wrap_status]. Without a hint the global
+//! [StatusMapper] classifies the error chain.
+//!
+//! Requires the `eyre` feature
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! fn load_user(id: u32) -> eyre::Result<String> {
+//!     Err(eyre::eyre!("no user with id {}", id)).wrap_status(StatusCode::NOT_FOUND)
+//! }
+//!
+//! let err: ApiError = load_user(42).unwrap_err().into();
+//!
+//! assert_eq!(err.status(), StatusCode::NOT_FOUND);
+//! assert_eq!(err.message(), Some("no user with id 42"));
+//! ```
+report_integration! {
+    feature: "eyre",
+    krate: eyre,
+    report: Report,
+    context: WrapErr::wrap_err,
+    ext: EyreStatusExt::wrap_status,
+}
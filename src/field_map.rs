@@ -0,0 +1,251 @@
+//! The map holding the additional fields of a problem.
+//!
+//! [FieldMap] has the same API with and without the feature
+//! `preserve-order`. The feature only changes the order in which the
+//! fields are iterated and serialized.
+use std::collections::HashMap;
+use std::iter::FromIterator;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+#[cfg(not(feature = "preserve-order"))]
+type Inner = HashMap<String, Value>;
+#[cfg(feature = "preserve-order")]
+type Inner = indexmap::IndexMap<String, Value>;
+
+/// The map holding the additional fields of a problem
+///
+/// The fields are kept in an arbitrary order unless the feature
+/// `preserve-order` is enabled which keeps the insertion order.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FieldMap {
+    inner: Inner,
+}
+
+impl FieldMap {
+    /// Creates an empty map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty map with at least the given capacity
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            inner: Inner::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the value of the field
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.inner.get(key)
+    }
+
+    /// Returns a mutable reference to the value of the field
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        self.inner.get_mut(key)
+    }
+
+    /// Returns `true` if the map contains the field
+    pub fn contains_key(&self, key: &str) -> bool {
+        self.inner.contains_key(key)
+    }
+
+    /// Inserts a field and returns the value it replaced
+    ///
+    /// A replaced field keeps its position.
+    pub fn insert(&mut self, key: String, value: Value) -> Option<Value> {
+        self.inner.insert(key, value)
+    }
+
+    /// Removes a field and returns its value
+    ///
+    /// The order of the remaining fields is kept.
+    pub fn remove(&mut self, key: &str) -> Option<Value> {
+        #[cfg(not(feature = "preserve-order"))]
+        return self.inner.remove(key);
+        #[cfg(feature = "preserve-order")]
+        return self.inner.shift_remove(key);
+    }
+
+    /// Keeps only the fields the predicate returns `true` for
+    pub fn retain<F>(&mut self, f: F)
+    where
+        F: FnMut(&String, &mut Value) -> bool,
+    {
+        self.inner.retain(f)
+    }
+
+    /// Removes all fields
+    pub fn clear(&mut self) {
+        self.inner.clear()
+    }
+
+    /// Returns the number of fields
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// Returns `true` if there are no fields
+    pub fn is_empty(&self) -> bool {
+        self.inner.is_empty()
+    }
+
+    /// Iterates over the fields
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.inner.iter())
+    }
+
+    /// Iterates over the fields with mutable values
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
+        IterMut(self.inner.iter_mut())
+    }
+
+    /// Iterates over the names of the fields
+    pub fn keys(&self) -> impl Iterator<Item = &String> + '_ {
+        self.inner.keys()
+    }
+
+    /// Iterates over the values of the fields
+    pub fn values(&self) -> impl Iterator<Item = &Value> + '_ {
+        self.inner.values()
+    }
+}
+
+impl From<HashMap<String, Value>> for FieldMap {
+    fn from(map: HashMap<String, Value>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl From<serde_json::Map<String, Value>> for FieldMap {
+    fn from(map: serde_json::Map<String, Value>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl FromIterator<(String, Value)> for FieldMap {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        Self {
+            inner: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<(String, Value)> for FieldMap {
+    fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, iter: I) {
+        self.inner.extend(iter)
+    }
+}
+
+impl IntoIterator for FieldMap {
+    type Item = (String, Value);
+    type IntoIter = IntoIter;
+
+    fn into_iter(self) -> IntoIter {
+        IntoIter(self.inner.into_iter())
+    }
+}
+
+impl<'a> IntoIterator for &'a FieldMap {
+    type Item = (&'a String, &'a Value);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut FieldMap {
+    type Item = (&'a String, &'a mut Value);
+    type IntoIter = IterMut<'a>;
+
+    fn into_iter(self) -> IterMut<'a> {
+        self.iter_mut()
+    }
+}
+
+#[cfg(not(feature = "preserve-order"))]
+type InnerIter<'a> = std::collections::hash_map::Iter<'a, String, Value>;
+#[cfg(feature = "preserve-order")]
+type InnerIter<'a> = indexmap::map::Iter<'a, String, Value>;
+
+#[cfg(not(feature = "preserve-order"))]
+type InnerIterMut<'a> = std::collections::hash_map::IterMut<'a, String, Value>;
+#[cfg(feature = "preserve-order")]
+type InnerIterMut<'a> = indexmap::map::IterMut<'a, String, Value>;
+
+#[cfg(not(feature = "preserve-order"))]
+type InnerIntoIter = std::collections::hash_map::IntoIter<String, Value>;
+#[cfg(feature = "preserve-order")]
+type InnerIntoIter = indexmap::map::IntoIter<String, Value>;
+
+/// An iterator over the fields of a [FieldMap]
+#[derive(Debug, Clone)]
+pub struct Iter<'a>(InnerIter<'a>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a String, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Iter<'_> {}
+
+/// An iterator over the fields of a [FieldMap] with mutable values
+#[derive(Debug)]
+pub struct IterMut<'a>(InnerIterMut<'a>);
+
+impl<'a> Iterator for IterMut<'a> {
+    type Item = (&'a String, &'a mut Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IterMut<'_> {}
+
+/// An owning iterator over the fields of a [FieldMap]
+#[derive(Debug)]
+pub struct IntoIter(InnerIntoIter);
+
+impl Iterator for IntoIter {
+    type Item = (String, Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.0.size_hint()
+    }
+}
+
+impl ExactSizeIterator for IntoIter {}
+
+#[cfg(feature = "json-schema")]
+impl schemars::JsonSchema for FieldMap {
+    fn schema_name() -> String {
+        <HashMap<String, Value>>::schema_name()
+    }
+
+    fn json_schema(gen: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+        <HashMap<String, Value>>::json_schema(gen)
+    }
+
+    fn is_referenceable() -> bool {
+        false
+    }
+}
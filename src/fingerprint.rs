@@ -0,0 +1,70 @@
+//! Stable fingerprints for grouping identical problems.
+//!
+//! A fingerprint identifies a class of errors rather than a single occurrence.
+//! Volatile data like `detail` and `instance` never becomes part of it.
+//!
+//! Fingerprints are FNV-1a hashes rendered as 16 hexadecimal digits. They
+//! do not depend on the process or platform so they can be compared
+//! across service instances.
+use serde_json::Value;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+pub(crate) struct Fingerprinter {
+    hash: u64,
+}
+
+impl Fingerprinter {
+    pub fn new() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+        }
+    }
+
+    /// Adds a component.
+    ///
+    /// Absent and empty components are distinguished from each other
+    /// and components are separated so that moving characters from one
+    /// component to another results in a different fingerprint.
+    pub fn add(&mut self, component: Option<&str>) -> &mut Self {
+        match component {
+            Some(component) => {
+                self.write(&[1]);
+                self.write(&(component.len() as u64).to_le_bytes());
+                self.write(component.as_bytes());
+            }
+            None => self.write(&[0]),
+        }
+        self
+    }
+
+    /// Adds a named JSON value.
+    pub fn add_value(&mut self, name: &str, value: Option<&Value>) -> &mut Self {
+        let value = value.map(Value::to_string);
+        self.add(Some(name)).add(value.as_deref())
+    }
+
+    /// Adds the type of a source error
+    ///
+    /// A source of an unknown type is distinguished from no source.
+    #[cfg(feature = "api-error")]
+    pub fn add_source_type(&mut self, has_source: bool, source_type: Option<&str>) -> &mut Self {
+        match (has_source, source_type) {
+            (false, _) => self.add(None),
+            (true, Some(source_type)) => self.add(Some(source_type)),
+            (true, None) => self.add(Some("")),
+        }
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! Conversion of reports of the `garde` crate into validation problems.
+//!
+//! Every error of a report becomes a [ValidationIssue] with the message
+//! of the error as `detail` and a JSON Pointer to the invalid value. This is
+//! the same shape as the problems created from the errors of `validator`.
+//! The issues are sorted by pointer.
+//!
+//! Requires the `garde` feature
+//!
+//! # Example
+//!
+//! ```rust
+//! use garde::{Error, Path, Report};
+//! use http_api_problem::*;
+//!
+//! let mut report = Report::new();
+//! report.append(Path::new("items").join(0).join("name"), Error::new("length is lower than 3"));
+//!
+//! let problem: HttpApiProblem = report.into();
+//!
+//! assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+//!
+//! let issues = problem.validation_issues().unwrap();
+//! assert_eq!(issues[0].pointer.as_deref(), Some("/items/0/name"));
+//! assert_eq!(issues[0].detail, "length is lower than 3");
+//! ```
+use garde::{Path, Report};
+
+use super::*;
+
+impl ValidationProblem {
+    /// Creates a validation problem from a [Report]
+    ///
+    /// The `type` is the one set with [set_validation_type_url].
+    ///
+    /// Requires the `garde` feature
+    pub fn from_garde_report(report: &Report) -> Self {
+        let mut issues: Vec<ValidationIssue> = report
+            .iter()
+            .map(|(path, error)| ValidationIssue::pointer(pointer(path), error.message()))
+            .collect();
+        issues.sort_by(|a, b| a.pointer.cmp(&b.pointer));
+
+        let mut problem: ValidationProblem = issues.into_iter().collect();
+        problem.type_url = validation_type_url();
+        problem
+    }
+}
+
+/// Turns a [Path] into a JSON Pointer
+///
+/// The components are taken from the serialized [Path] which is a list of
+/// `[kind, component]` pairs from the outermost to the innermost component.
+/// Components without a key, e.g. of newtypes, do not appear in the
+/// serialized value of the validated type and are skipped.
+fn pointer(path: &Path) -> String {
+    #[derive(Deserialize, PartialEq)]
+    #[serde(rename_all = "snake_case")]
+    enum ComponentKind {
+        None,
+        Key,
+        Index,
+    }
+
+    let components: Vec<(ComponentKind, String)> = serde_json::to_value(path)
+        .and_then(serde_json::from_value)
+        .unwrap_or_default();
+
+    json_pointer(
+        components
+            .iter()
+            .filter(|(kind, _)| *kind != ComponentKind::None)
+            .map(|(_, component)| component),
+    )
+}
+
+impl From<Report> for ValidationProblem {
+    fn from(report: Report) -> Self {
+        ValidationProblem::from_garde_report(&report)
+    }
+}
+
+impl From<Report> for HttpApiProblem {
+    fn from(report: Report) -> Self {
+        ValidationProblem::from_garde_report(&report).into_http_api_problem()
+    }
+}
+
+#[cfg(feature = "api-error")]
+impl From<Report> for ApiError {
+    fn from(report: Report) -> Self {
+        ValidationProblem::from_garde_report(&report).into_api_error()
+    }
+}
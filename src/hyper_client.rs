@@ -0,0 +1,81 @@
+//! Integration with [hyper] clients.
+//!
+//! Requires the `hyper-client` feature
+use std::error::Error;
+use std::future::Future;
+
+use http_body_util::{BodyExt, Limited};
+use hyper::body::Body;
+
+use super::*;
+
+/// Extension trait for [hyper::Response]
+///
+/// Requires the `hyper-client` feature
+pub trait HyperResponseExt: Sized {
+    /// Turns a response with a status other than `2xx` into an error
+    /// containing the problem sent by the server.
+    ///
+    /// At most [DEFAULT_PROBLEM_BODY_LIMIT] bytes of the body are collected.
+    /// See [HyperResponseExt::error_for_problem_with_limit].
+    fn error_for_problem(self) -> impl Future<Output = Result<Self, ClientProblemError>> + Send {
+        self.error_for_problem_with_limit(DEFAULT_PROBLEM_BODY_LIMIT)
+    }
+
+    /// Turns a response with a status other than `2xx` into an error
+    /// containing the problem sent by the server.
+    ///
+    /// The body of a successful response is left untouched. Otherwise at most
+    /// `limit` bytes of the body are collected to extract the problem.
+    /// A larger body results in a [ClientProblemError::Transport].
+    /// See [HttpApiProblem::from_http_response] on how the problem is
+    /// extracted.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    /// use http_body_util::Full;
+    /// use hyper::body::Bytes;
+    ///
+    /// # #[tokio::main(flavor = "current_thread")]
+    /// # async fn main() {
+    /// let response = hyper::Response::builder()
+    ///     .status(StatusCode::NOT_FOUND)
+    ///     .body(Full::new(Bytes::from_static(b"nothing here")))
+    ///     .unwrap();
+    ///
+    /// let err = response.error_for_problem_with_limit(1024).await.unwrap_err();
+    ///
+    /// assert_eq!(Some(StatusCode::NOT_FOUND), err.status());
+    /// # }
+    /// ```
+    fn error_for_problem_with_limit(
+        self,
+        limit: usize,
+    ) -> impl Future<Output = Result<Self, ClientProblemError>> + Send;
+}
+
+impl<B> HyperResponseExt for hyper::Response<B>
+where
+    B: Body + Send,
+    B::Data: Send,
+    B::Error: Into<Box<dyn Error + Send + Sync + 'static>>,
+{
+    async fn error_for_problem_with_limit(self, limit: usize) -> Result<Self, ClientProblemError> {
+        if self.status().is_success() {
+            return Ok(self);
+        }
+
+        let (parts, body) = self.into_parts();
+        let body = Limited::new(body, limit)
+            .collect()
+            .await
+            .map_err(ClientProblemError::Transport)?
+            .to_bytes();
+
+        Err(ClientProblemError::Problem(Box::new(
+            ProblemResponse::from_parts(parts.status, parts.headers, &body),
+        )))
+    }
+}
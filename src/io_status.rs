@@ -0,0 +1,119 @@
+//! Mapping of IO errors to statuses.
+//!
+//! `From<io::Error> for ApiError` determines the status with the mapper
+//! installed via [set_io_error_status_mapper] which defaults to
+//! [default_io_error_status]. To get the former behaviour of always
+//! responding with a `500 - Internal Server Error` install
+//! [blanket_io_error_status].
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::io;
+//! use http_api_problem::*;
+//!
+//! let err: ApiError = io::Error::from(io::ErrorKind::NotFound).into();
+//! assert_eq!(err.status(), StatusCode::NOT_FOUND);
+//!
+//! set_io_error_status_mapper(blanket_io_error_status);
+//! let err: ApiError = io::Error::from(io::ErrorKind::NotFound).into();
+//! assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+//! # clear_io_error_status_mapper();
+//! ```
+use std::io::{self, ErrorKind};
+use std::sync::{Arc, RwLock};
+
+use super::*;
+
+type Mapper = Arc<dyn Fn(&io::Error) -> StatusCode + Send + Sync>;
+
+static IO_ERROR_STATUS_MAPPER: RwLock<Option<Mapper>> = RwLock::new(None);
+
+/// Installs the mapper which determines the status of an [ApiError]
+/// created from an [io::Error].
+///
+/// A custom mapper can fall back to [default_io_error_status].
+pub fn set_io_error_status_mapper<F>(mapper: F)
+where
+    F: Fn(&io::Error) -> StatusCode + Send + Sync + 'static,
+{
+    let mut guard = IO_ERROR_STATUS_MAPPER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(Arc::new(mapper));
+}
+
+/// Restores [default_io_error_status] as the mapper
+pub fn clear_io_error_status_mapper() {
+    let mut guard = IO_ERROR_STATUS_MAPPER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = None;
+}
+
+/// Maps every [io::Error] to `500 - Internal Server Error`
+pub fn blanket_io_error_status(_error: &io::Error) -> StatusCode {
+    StatusCode::INTERNAL_SERVER_ERROR
+}
+
+/// Maps an [io::Error] to a status by its [ErrorKind]
+///
+/// | Kind | Status |
+/// |------|--------|
+/// | `NotFound` | `404` |
+/// | `PermissionDenied` | `403` |
+/// | `AlreadyExists` | `409` |
+/// | `InvalidInput`, `InvalidData`, `UnexpectedEof` | `400` |
+/// | `FileTooLarge` | `413` |
+/// | `Unsupported` | `501` |
+/// | `ConnectionRefused`, `ConnectionReset`, `ConnectionAborted`, `NotConnected`, `BrokenPipe`, `HostUnreachable`, `NetworkUnreachable`, `NetworkDown` | `502` |
+/// | `WouldBlock`, `Interrupted`, `ResourceBusy`, `ReadOnlyFilesystem` | `503` |
+/// | `TimedOut` | `504` |
+/// | `StorageFull` | `507` |
+/// | all others | `500` |
+///
+/// `FileTooLarge`, `HostUnreachable`, `NetworkUnreachable`, `NetworkDown`,
+/// `ResourceBusy`, `ReadOnlyFilesystem` and `StorageFull` were stabilized
+/// with Rust 1.83. If the crate is built with an older compiler they
+/// are mapped to `500`.
+pub fn default_io_error_status(error: &io::Error) -> StatusCode {
+    match error.kind() {
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::PermissionDenied => StatusCode::FORBIDDEN,
+        ErrorKind::AlreadyExists => StatusCode::CONFLICT,
+        ErrorKind::InvalidInput | ErrorKind::InvalidData | ErrorKind::UnexpectedEof => {
+            StatusCode::BAD_REQUEST
+        }
+        ErrorKind::Unsupported => StatusCode::NOT_IMPLEMENTED,
+        ErrorKind::ConnectionRefused
+        | ErrorKind::ConnectionReset
+        | ErrorKind::ConnectionAborted
+        | ErrorKind::NotConnected
+        | ErrorKind::BrokenPipe => StatusCode::BAD_GATEWAY,
+        ErrorKind::WouldBlock | ErrorKind::Interrupted => StatusCode::SERVICE_UNAVAILABLE,
+        ErrorKind::TimedOut => StatusCode::GATEWAY_TIMEOUT,
+        #[cfg(rustc_1_83)]
+        ErrorKind::FileTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        #[cfg(rustc_1_83)]
+        ErrorKind::HostUnreachable | ErrorKind::NetworkUnreachable | ErrorKind::NetworkDown => {
+            StatusCode::BAD_GATEWAY
+        }
+        #[cfg(rustc_1_83)]
+        ErrorKind::ResourceBusy | ErrorKind::ReadOnlyFilesystem => StatusCode::SERVICE_UNAVAILABLE,
+        #[cfg(rustc_1_83)]
+        ErrorKind::StorageFull => StatusCode::INSUFFICIENT_STORAGE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+pub(crate) fn io_error_status(error: &io::Error) -> StatusCode {
+    let mapper = IO_ERROR_STATUS_MAPPER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone();
+
+    match mapper {
+        Some(mapper) => mapper(error),
+        None => default_io_error_status(error),
+    }
+}
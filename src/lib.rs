@@ -26,6 +26,12 @@
 //! [HttpApiProblem] implements [Serialize] and [Deserialize] for
 //! [HttpApiProblem].
 //!
+//! If the additional fields of a problem are known in advance,
+//! [TypedProblem] can be used to have them strongly typed.
+//!
+//! For hot paths [HttpApiProblemRef] serializes borrowed and
+//! pre-serialized data without intermediate allocations.
+//!
 //! ## Examples
 //!
 //! ```rust
@@ -71,6 +77,24 @@
 //! used as a fallback. This can be easily avoided by only using those constructor
 //! functions which require a [StatusCode].
 //!
+//! ## Clients
+//!
+//! [HttpApiProblem::from_http_response] extracts a problem from a response
+//! received by a client.
+//!
+//! The feature `reqwest` adds [ReqwestResponseExt::error_for_problem] to
+//! `reqwest::Response` which turns responses with a status other than `2xx`
+//! into a [ClientProblemError]. The feature `hyper-client` does the same
+//! for `hyper::Response` via [HyperResponseExt::error_for_problem].
+//!
+//! [HttpApiProblem::retry_advice] tells whether a failed request
+//! should be retried.
+//!
+//! ## Validation
+//!
+//! [HttpApiProblem::validate] checks a problem against the rules of
+//! RFC 7807 and RFC 9457, e.g. in tests or debug builds.
+//!
 //! ## Features
 //!
 //! ### JsonSchema
@@ -78,6 +102,19 @@
 //! The feature `json-schema` enables a derived implementation for
 //! JsonSchema, via `schemars`.
 //!
+//! ### Url
+//!
+//! The feature `url` enables resolving relative `type` and `instance`
+//! URIs against a base URI via [HttpApiProblem::resolve_against].
+//!
+//! ### Preserve order
+//!
+//! The feature `preserve-order` keeps the additional fields in a
+//! [FieldMap] in the order they were added so that they are serialized
+//! in that order. The API of [FieldMap] does not change with the feature.
+//! Independent of this feature [HttpApiProblem::sorted] serializes the
+//! additional fields sorted by their keys.
+//!
 //! ### Web Frameworks
 //!
 //! There are multiple features to integrate with web frameworks:
@@ -104,6 +141,22 @@
 //! return from "api handlers" that generate responses and can be
 //! converted into an `HttpApiProblem`.
 //!
+//! Server errors are handed to an [ErrorReporter] (if one is registered)
+//! whenever an `ApiError` is converted into a response of a web framework.
+//!
+//! ### Minimum supported Rust version
+//!
+//! The minimum supported Rust version is 1.79. Some features depend on
+//! crates whose current releases require a newer Rust version. These
+//! features are tested with the latest stable Rust only:
+//!
+//! * `preserve-order`: Rust 1.85 (`indexmap`)
+//! * `url`: Rust 1.88 (`idna`)
+//! * `reqwest`: Rust 1.88 (`reqwest` 0.13 itself requires 1.85, `idna`)
+//! * `validator`: Rust 1.88 (`idna`)
+//! * `garde`: Rust 1.84 (`garde`)
+//! * `sqlx` and `sqlx-mysql`: Rust 1.88 (`idna`)
+//!
 //! ## License
 //!
 //! `http-api-problem` is primarily distributed under the terms of both the MIT
@@ -116,12 +169,86 @@ use std::fmt;
 
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
+
+pub mod field_map;
+pub use field_map::FieldMap;
+mod fingerprint;
+mod macros;
+mod typed;
+pub use typed::*;
+mod borrowed;
+pub use borrowed::*;
+mod parse;
+pub use parse::*;
+mod conformance;
+pub use conformance::*;
+mod client;
+pub use client::*;
+mod retry;
+pub use retry::*;
+mod collection;
+pub use collection::*;
+mod validation;
+pub use validation::*;
+#[cfg(feature = "garde")]
+mod garde_report;
+#[cfg(feature = "reqwest")]
+mod reqwest_client;
+#[cfg(feature = "validator")]
+mod validator_errors;
+#[cfg(feature = "reqwest")]
+pub use reqwest_client::*;
+#[cfg(feature = "hyper-client")]
+mod hyper_client;
+#[cfg(feature = "hyper-client")]
+pub use hyper_client::*;
+#[cfg(feature = "url")]
+mod resolve;
+#[cfg(feature = "tonic")]
+mod tonic_status;
+#[cfg(feature = "tonic")]
+pub use tonic_status::*;
 
 #[cfg(feature = "api-error")]
 mod api_error;
 #[cfg(feature = "api-error")]
 pub use api_error::*;
+#[cfg(feature = "api-error")]
+mod reporting;
+#[cfg(feature = "api-error")]
+pub use reporting::*;
+#[cfg(feature = "api-error")]
+mod io_status;
+#[cfg(feature = "api-error")]
+pub use io_status::*;
+#[cfg(feature = "api-error")]
+mod status_mapper;
+#[cfg(feature = "api-error")]
+pub use status_mapper::*;
+#[cfg(feature = "api-error")]
+mod result_ext;
+#[cfg(feature = "api-error")]
+pub use result_ext::*;
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+#[macro_use]
+mod report_integration;
+#[cfg(feature = "anyhow")]
+mod anyhow_error;
+#[cfg(feature = "anyhow")]
+pub use anyhow_error::*;
+#[cfg(feature = "eyre")]
+mod eyre_report;
+#[cfg(feature = "eyre")]
+pub use eyre_report::*;
+#[cfg(feature = "sqlx")]
+mod sqlx_error;
+#[cfg(feature = "sqlx")]
+pub use sqlx_error::*;
+#[cfg(feature = "api-error")]
+mod upstream;
+#[cfg(feature = "api-error")]
+pub use upstream::*;
 
 #[cfg(feature = "json-schema")]
 use schemars::JsonSchema;
@@ -139,6 +266,11 @@ pub use http::status::{InvalidStatusCode, StatusCode};
 /// "application/problem+json"
 pub static PROBLEM_JSON_MEDIA_TYPE: &str = "application/problem+json";
 
+/// The recommended media type when serialized to XML
+///
+/// "application/problem+xml"
+pub static PROBLEM_XML_MEDIA_TYPE: &str = "application/problem+xml";
+
 /// Description of a problem that can be returned by an HTTP API
 /// based on [RFC7807](https://tools.ietf.org/html/rfc7807)
 ///
@@ -175,7 +307,8 @@ pub static PROBLEM_JSON_MEDIA_TYPE: &str = "application/problem+json";
 /// [StatusCode] contained. In this case the `status` field will be empty.
 /// This is a trade off so that the recipient does not have to deal with
 /// another error and can still have access to the remaining fields of the
-/// struct.
+/// struct. Use [HttpApiProblem::parse_strict] or [HttpApiProblem::parse_lenient]
+/// for more control over invalid members.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 #[cfg_attr(feature = "json-schema", derive(JsonSchema))]
 #[cfg_attr(
@@ -239,7 +372,7 @@ pub struct HttpApiProblem {
     /// These values get serialized into the JSON
     /// on top level.
     #[serde(flatten)]
-    additional_fields: HashMap<String, serde_json::Value>,
+    additional_fields: FieldMap,
 }
 
 impl HttpApiProblem {
@@ -625,7 +758,7 @@ impl HttpApiProblem {
     ///
     /// If the key does not exist or the field is not deserializable to
     /// the target type `None` is returned
-    pub fn additional_fields(&self) -> &HashMap<String, Value> {
+    pub fn additional_fields(&self) -> &FieldMap {
         &self.additional_fields
     }
 
@@ -633,7 +766,7 @@ impl HttpApiProblem {
     ///
     /// If the key does not exist or the field is not deserializable to
     /// the target type `None` is returned
-    pub fn additional_fields_mut(&mut self) -> &mut HashMap<String, Value> {
+    pub fn additional_fields_mut(&mut self) -> &mut FieldMap {
         &mut self.additional_fields
     }
 
@@ -649,6 +782,56 @@ impl HttpApiProblem {
         self.additional_fields.get(key)
     }
 
+    /// Returns a stable fingerprint for the class of this problem.
+    ///
+    /// The fingerprint is computed from `status`, `type` and `title`.
+    /// The volatile members `detail` and `instance` as well as the
+    /// additional fields are not taken into account.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let a = HttpApiProblem::with_title(StatusCode::NOT_FOUND).instance("/users/1");
+    /// let b = HttpApiProblem::with_title(StatusCode::NOT_FOUND).instance("/users/2");
+    /// let c = HttpApiProblem::with_title(StatusCode::GONE).instance("/users/1");
+    ///
+    /// assert_eq!(a.fingerprint(), b.fingerprint());
+    /// assert_ne!(a.fingerprint(), c.fingerprint());
+    /// ```
+    pub fn fingerprint(&self) -> String {
+        self.fingerprint_with_fields(&[])
+    }
+
+    /// Returns a stable fingerprint for the class of this problem
+    /// which also takes the given additional fields into account.
+    ///
+    /// See [HttpApiProblem::fingerprint]
+    pub fn fingerprint_with_fields(&self, fields: &[&str]) -> String {
+        let status = self.status.map(|status| status.as_u16().to_string());
+
+        let mut fingerprinter = fingerprint::Fingerprinter::new();
+        fingerprinter
+            .add(status.as_deref())
+            .add(self.type_url.as_deref())
+            .add(self.title.as_deref());
+        for field in fields {
+            fingerprinter.add_value(field, self.json_value(field));
+        }
+
+        fingerprinter.finish()
+    }
+
+    /// Adds the [fingerprint](HttpApiProblem::fingerprint) as the
+    /// additional field `fingerprint`.
+    ///
+    /// This allows support tooling to group problems reported by clients.
+    pub fn expose_fingerprint(&mut self) {
+        let fingerprint = self.fingerprint();
+        self.set_value("fingerprint", &fingerprint);
+    }
+
     /// Serialize to a JSON `Vec<u8>`
     pub fn json_bytes(&self) -> Vec<u8> {
         serde_json::to_vec(self).unwrap()
@@ -659,6 +842,46 @@ impl HttpApiProblem {
         serde_json::to_string_pretty(self).unwrap()
     }
 
+    /// Returns a view of this problem which serializes the additional
+    /// fields sorted by their keys.
+    ///
+    /// The standard members always come first in the order of the RFC
+    /// (`type`, `status`, `title`, `detail`, `instance`). This makes the output
+    /// deterministic regardless of the order in which fields were added.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let p = HttpApiProblem::new(StatusCode::NOT_FOUND)
+    ///     .value("b", &2)
+    ///     .value("a", &1)
+    ///     .title("Not Found");
+    ///
+    /// assert_eq!(
+    ///     serde_json::to_string(&p.sorted()).unwrap(),
+    ///     r#"{"status":404,"title":"Not Found","a":1,"b":2}"#
+    /// );
+    /// ```
+    pub fn sorted(&self) -> SortedHttpApiProblem<'_> {
+        SortedHttpApiProblem(self)
+    }
+
+    /// Serialize to a JSON `Vec<u8>` with the additional fields sorted by their keys
+    ///
+    /// See [HttpApiProblem::sorted]
+    pub fn json_bytes_sorted(&self) -> Vec<u8> {
+        serde_json::to_vec(&self.sorted()).unwrap()
+    }
+
+    /// Serialize to a JSON `String` with the additional fields sorted by their keys
+    ///
+    /// See [HttpApiProblem::sorted]
+    pub fn json_string_sorted(&self) -> String {
+        serde_json::to_string_pretty(&self.sorted()).unwrap()
+    }
+
     /// Creates a [hyper] response.
     ///
     /// If status is `None` `500 - Internal Server Error` is the
@@ -907,6 +1130,48 @@ impl From<std::convert::Infallible> for HttpApiProblem {
     }
 }
 
+/// Serializes an [HttpApiProblem] with the additional fields sorted by their keys.
+///
+/// Created by [HttpApiProblem::sorted].
+#[derive(Debug, Clone, Copy)]
+pub struct SortedHttpApiProblem<'a>(&'a HttpApiProblem);
+
+impl Serialize for SortedHttpApiProblem<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct Sorted<'a> {
+            #[serde(rename = "type")]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            type_url: Option<&'a str>,
+            #[serde(with = "custom_http_status_serialization")]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            status: Option<StatusCode>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            title: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            detail: Option<&'a str>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            instance: Option<&'a str>,
+            #[serde(flatten)]
+            additional_fields: BTreeMap<&'a String, &'a Value>,
+        }
+
+        let problem = self.0;
+        Sorted {
+            type_url: problem.type_url.as_deref(),
+            status: problem.status,
+            title: problem.title.as_deref(),
+            detail: problem.detail.as_deref(),
+            instance: problem.instance.as_deref(),
+            additional_fields: problem.additional_fields.iter().collect(),
+        }
+        .serialize(serializer)
+    }
+}
+
 /// Creates an [hyper::Response] from something that can become an
 /// `HttpApiProblem`.
 ///
@@ -0,0 +1,207 @@
+//! Macros for the concise construction of problems and errors.
+//!
+//! All macros take a status as the first argument. A literal like `404` is
+//! checked at compile time, any other expression must implement
+//! `Into<StatusCode>`. It can be followed by a format string with arguments
+//! which becomes the `detail` (or the message of an [ApiError]) and by
+//! additional fields after a `;`:
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let id = 42;
+//! let p = problem!(404, "User {} not found", id; field "user_id" = id);
+//!
+//! assert_eq!(p.status, Some(StatusCode::NOT_FOUND));
+//! assert_eq!(p.detail.as_deref(), Some("User 42 not found"));
+//! assert_eq!(p.get_value::<&str, u32>("user_id"), Some(42));
+//! ```
+
+/// Turns the status argument of the macros into a [StatusCode](crate::StatusCode)
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __http_api_problem_status {
+    ($status:literal) => {{
+        const _: () = assert!(
+            100 <= $status && $status <= 999,
+            "a status code must be within 100 and 999"
+        );
+        match $crate::StatusCode::from_u16($status) {
+            Ok(status) => status,
+            Err(_) => unreachable!(),
+        }
+    }};
+    ($status:expr) => {
+        ::core::convert::Into::<$crate::StatusCode>::into($status)
+    };
+}
+
+/// Creates an [HttpApiProblem](crate::HttpApiProblem)
+///
+/// The title is derived from the status. A format string becomes
+/// the `detail`.
+///
+/// #Example
+///
+/// ```rust
+/// use http_api_problem::*;
+///
+/// let p = problem!(StatusCode::CONFLICT);
+/// assert_eq!(p, HttpApiProblem::with_title(StatusCode::CONFLICT));
+///
+/// let version = 3;
+/// let p = problem!(409, "version {version} is outdated"; field "current" = 4);
+/// assert_eq!(p.detail.as_deref(), Some("version 3 is outdated"));
+/// assert_eq!(p.get_value::<&str, u32>("current"), Some(4));
+/// ```
+///
+/// Invalid literal statuses are rejected at compile time:
+///
+/// ```rust,compile_fail
+/// use http_api_problem::*;
+///
+/// let p = problem!(1000);
+/// ```
+#[macro_export]
+macro_rules! problem {
+    (@status { $($status:tt)* }) => {
+        $crate::HttpApiProblem::with_title($($status)*)
+    };
+    (@status { $($status:tt)* }; $(field $name:literal = $value:expr),+ $(,)?) => {
+        $crate::problem!(@status { $($status)* })
+            $(.value($name, &$value))+
+    };
+    (@status { $($status:tt)* }, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::problem!(@status { $($status)* })
+            .detail(::std::format!($fmt $(, $arg)*))
+    };
+    (@status { $($status:tt)* }, $fmt:literal $(, $arg:expr)*; $(field $name:literal = $value:expr),+ $(,)?) => {
+        $crate::problem!(@status { $($status)* }, $fmt $(, $arg)*)
+            $(.value($name, &$value))+
+    };
+    ($status:literal $($rest:tt)*) => {
+        $crate::problem!(@status { $crate::__http_api_problem_status!($status) } $($rest)*)
+    };
+    ($status:expr) => {
+        $crate::problem!(@status { $crate::__http_api_problem_status!($status) })
+    };
+    ($status:expr, $($rest:tt)*) => {
+        $crate::problem!(@status { $crate::__http_api_problem_status!($status) }, $($rest)*)
+    };
+    ($status:expr; $($rest:tt)*) => {
+        $crate::problem!(@status { $crate::__http_api_problem_status!($status) }; $($rest)*)
+    };
+}
+
+/// Creates an [ApiError](crate::ApiError)
+///
+/// A format string becomes the message.
+///
+/// Requires the `api-error` feature
+///
+/// #Example
+///
+/// ```rust
+/// use http_api_problem::*;
+///
+/// let id = 42;
+/// let err = api_error!(404, "User {} not found", id; field "user_id" = id);
+///
+/// assert_eq!(err.status(), StatusCode::NOT_FOUND);
+/// assert_eq!(err.message(), Some("User 42 not found"));
+/// assert_eq!(err.fields().get("user_id"), Some(&serde_json::json!(42)));
+/// ```
+#[cfg(feature = "api-error")]
+#[macro_export]
+macro_rules! api_error {
+    (@status { $($status:tt)* }) => {
+        $crate::ApiError::new($($status)*)
+    };
+    (@status { $($status:tt)* }; $(field $name:literal = $value:expr),+ $(,)?) => {
+        $crate::ApiError::builder($($status)*)
+            $(.field($name, $value))+
+            .finish()
+    };
+    (@status { $($status:tt)* }, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::ApiError::builder($($status)*)
+            .message(::std::format!($fmt $(, $arg)*))
+            .finish()
+    };
+    (@status { $($status:tt)* }, $fmt:literal $(, $arg:expr)*; $(field $name:literal = $value:expr),+ $(,)?) => {
+        $crate::ApiError::builder($($status)*)
+            .message(::std::format!($fmt $(, $arg)*))
+            $(.field($name, $value))+
+            .finish()
+    };
+    ($status:literal $($rest:tt)*) => {
+        $crate::api_error!(@status { $crate::__http_api_problem_status!($status) } $($rest)*)
+    };
+    ($status:expr) => {
+        $crate::api_error!(@status { $crate::__http_api_problem_status!($status) })
+    };
+    ($status:expr, $($rest:tt)*) => {
+        $crate::api_error!(@status { $crate::__http_api_problem_status!($status) }, $($rest)*)
+    };
+    ($status:expr; $($rest:tt)*) => {
+        $crate::api_error!(@status { $crate::__http_api_problem_status!($status) }; $($rest)*)
+    };
+}
+
+/// Returns early with an [ApiError](crate::ApiError)
+///
+/// Takes the same arguments as [api_error!]. The error is converted with
+/// `Into` so that it can be returned from functions with other error types.
+///
+/// Requires the `api-error` feature
+///
+/// #Example
+///
+/// ```rust
+/// use http_api_problem::*;
+///
+/// fn update(version: u32) -> Result<(), ApiError> {
+///     if version < 4 {
+///         bail_api!(409, "version {} is outdated", version);
+///     }
+///     Ok(())
+/// }
+///
+/// assert_eq!(update(3).unwrap_err().status(), StatusCode::CONFLICT);
+/// ```
+#[cfg(feature = "api-error")]
+#[macro_export]
+macro_rules! bail_api {
+    ($($args:tt)+) => {
+        return ::core::result::Result::Err(::core::convert::Into::into($crate::api_error!($($args)+)))
+    };
+}
+
+/// Returns early with an [ApiError](crate::ApiError) if the condition
+/// is not met
+///
+/// The condition is followed by the same arguments as [api_error!].
+///
+/// Requires the `api-error` feature
+///
+/// #Example
+///
+/// ```rust
+/// use http_api_problem::*;
+///
+/// fn set_age(age: i32) -> Result<(), ApiError> {
+///     ensure_api!(age >= 0, 400, "age must not be negative"; field "age" = age);
+///     Ok(())
+/// }
+///
+/// assert!(set_age(30).is_ok());
+/// assert_eq!(set_age(-1).unwrap_err().status(), StatusCode::BAD_REQUEST);
+/// ```
+#[cfg(feature = "api-error")]
+#[macro_export]
+macro_rules! ensure_api {
+    ($cond:expr, $($args:tt)+) => {
+        if !$cond {
+            $crate::bail_api!($($args)+);
+        }
+    };
+}
@@ -0,0 +1,233 @@
+//! Strict and lenient parsing of problems.
+//!
+//! Deserializing an [HttpApiProblem] via serde silently drops an invalid
+//! `status` and fails completely if one of the other standard members has
+//! the wrong type. The functions here give more control:
+//!
+//! * [HttpApiProblem::parse_lenient] accepts whatever it can and drops
+//!   invalid standard members as
+//!   [RFC 9457](https://www.rfc-editor.org/rfc/rfc9457#section-3.1)
+//!   recommends ignoring them.
+//! * [HttpApiProblem::parse_strict] reports every invalid member.
+use std::error::Error;
+use std::fmt;
+
+use serde_json::{Map, Value};
+
+use super::*;
+
+/// An error returned when parsing a problem failed.
+#[derive(Debug)]
+pub enum ProblemParseError {
+    /// The input is not valid JSON
+    Json(serde_json::Error),
+    /// The input is valid JSON but not an object
+    NotAnObject,
+    /// Standard members have invalid values
+    ///
+    /// Only returned by [HttpApiProblem::parse_strict]
+    InvalidMembers(Vec<InvalidMember>),
+}
+
+impl fmt::Display for ProblemParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProblemParseError::Json(err) => write!(f, "invalid JSON: {}", err),
+            ProblemParseError::NotAnObject => write!(f, "a problem must be a JSON object"),
+            ProblemParseError::InvalidMembers(members) => {
+                write!(f, "invalid members: ")?;
+                for (idx, member) in members.iter().enumerate() {
+                    if idx > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", member)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Error for ProblemParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ProblemParseError::Json(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProblemParseError {
+    fn from(err: serde_json::Error) -> Self {
+        ProblemParseError::Json(err)
+    }
+}
+
+/// A standard member with an invalid value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidMember {
+    /// The name of the member, e.g. `status`
+    pub name: String,
+    /// The value found in the input
+    pub value: Value,
+    /// What was expected instead
+    pub expected: &'static str,
+}
+
+impl fmt::Display for InvalidMember {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' must be {} but was {}",
+            self.name, self.expected, self.value
+        )
+    }
+}
+
+impl HttpApiProblem {
+    /// Parses a problem and drops standard members with invalid values.
+    ///
+    /// The invalid members are not kept as additional fields since
+    /// their names are reserved. Use [HttpApiProblem::parse_strict] to
+    /// find out which members are invalid.
+    ///
+    /// A `status` is also accepted if it is a string containing
+    /// a status code. `null` values of standard members are treated
+    /// as if the members were absent.
+    ///
+    /// Fails only if the input is not a JSON object.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let json = r#"{"status": "404", "title": 42, "detail": "gone"}"#;
+    ///
+    /// let p = HttpApiProblem::parse_lenient(json).unwrap();
+    ///
+    /// assert_eq!(Some(StatusCode::NOT_FOUND), p.status);
+    /// assert_eq!(None, p.title);
+    /// assert_eq!(Some("gone"), p.detail.as_deref());
+    /// assert_eq!(None, p.json_value("title"));
+    /// ```
+    pub fn parse_lenient<T: AsRef<[u8]>>(json: T) -> Result<Self, ProblemParseError> {
+        let (problem, _) = parse(json.as_ref(), false)?;
+        Ok(problem)
+    }
+
+    /// Parses a problem and fails if a standard member has an invalid value.
+    ///
+    /// The error contains all invalid members. A `status` must be a number
+    /// between 100 and 599. `null` is not accepted for any standard member.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    ///
+    /// let json = r#"{"status": 999, "title": 42}"#;
+    ///
+    /// match HttpApiProblem::parse_strict(json) {
+    ///     Err(ProblemParseError::InvalidMembers(members)) => {
+    ///         let names: Vec<_> = members.iter().map(|m| m.name.as_str()).collect();
+    ///         assert_eq!(names, vec!["status", "title"]);
+    ///     }
+    ///     _ => panic!("expected invalid members"),
+    /// }
+    /// ```
+    pub fn parse_strict<T: AsRef<[u8]>>(json: T) -> Result<Self, ProblemParseError> {
+        let (problem, invalid) = parse(json.as_ref(), true)?;
+        if invalid.is_empty() {
+            Ok(problem)
+        } else {
+            Err(ProblemParseError::InvalidMembers(invalid))
+        }
+    }
+}
+
+fn parse(
+    json: &[u8],
+    strict: bool,
+) -> Result<(HttpApiProblem, Vec<InvalidMember>), ProblemParseError> {
+    match serde_json::from_slice(json)? {
+        Value::Object(members) => Ok(parse_members(members, strict)),
+        _ => Err(ProblemParseError::NotAnObject),
+    }
+}
+
+/// Creates a problem from the members of a JSON object and returns
+/// the standard members which were invalid
+///
+/// Invalid members are dropped. `null` values are only reported if
+/// parsing is strict.
+pub(crate) fn parse_members(
+    members: Map<String, Value>,
+    strict: bool,
+) -> (HttpApiProblem, Vec<InvalidMember>) {
+    let mut problem = HttpApiProblem::empty();
+    let mut invalid = Vec::new();
+
+    for (name, value) in members {
+        let valid = match name.as_str() {
+            "status" => parse_status(&value, strict)
+                .map(|status| problem.status = Some(status))
+                .is_some(),
+            "type" => as_string(&value)
+                .map(|type_url| problem.type_url = Some(type_url))
+                .is_some(),
+            "title" => as_string(&value)
+                .map(|title| problem.title = Some(title))
+                .is_some(),
+            "detail" => as_string(&value)
+                .map(|detail| problem.detail = Some(detail))
+                .is_some(),
+            "instance" => as_string(&value)
+                .map(|instance| problem.instance = Some(instance))
+                .is_some(),
+            _ => {
+                problem.additional_fields.insert(name, value);
+                continue;
+            }
+        };
+
+        if valid || (value.is_null() && !strict) {
+            continue;
+        }
+
+        invalid.push(InvalidMember {
+            expected: expected_for(&name, strict),
+            name,
+            value,
+        });
+    }
+
+    (problem, invalid)
+}
+
+fn as_string(value: &Value) -> Option<String> {
+    value.as_str().map(ToOwned::to_owned)
+}
+
+fn parse_status(value: &Value, strict: bool) -> Option<StatusCode> {
+    let code = match value {
+        Value::Number(n) => n.as_u64()?,
+        Value::String(s) if !strict => s.trim().parse().ok()?,
+        _ => return None,
+    };
+
+    if !(100..=599).contains(&code) {
+        return None;
+    }
+
+    StatusCode::from_u16(code as u16).ok()
+}
+
+fn expected_for(name: &str, strict: bool) -> &'static str {
+    match name {
+        "status" if strict => "a number between 100 and 599",
+        "status" => "a status code between 100 and 599",
+        "type" | "instance" => "a URI reference string",
+        _ => "a string",
+    }
+}
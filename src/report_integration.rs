@@ -0,0 +1,55 @@
+//! The integrations of `anyhow` and `eyre` which share the same shape.
+//!
+//! Both crates provide a type erased report which carries context and can be
+//! downcast to each of its layers. `report_integration!` generates the
+//! extension trait to attach a [StatusHint](crate::StatusHint) and the
+//! conversion into [ApiError](crate::ApiError) for one of them.
+
+/// Generates the extension trait and the conversion into `ApiError` for a
+/// report type
+///
+/// The name of the method of the extension trait differs between the
+/// integrations so that both traits can be in scope at the same time.
+macro_rules! report_integration {
+    (
+        feature: $feature:literal,
+        krate: $krate:ident,
+        report: $report:ident,
+        context: $context_trait:ident::$context_fn:ident,
+        ext: $ext:ident::$method:ident,
+    ) => {
+        use std::error::Error;
+
+        use super::*;
+
+        /// Attaches a status to the error of a result
+        ///
+        #[doc = concat!("Requires the `", $feature, "` feature")]
+        pub trait $ext<T, E> {
+            /// Adds a [StatusHint] as context to the error
+            fn $method(self, status: StatusCode) -> $krate::Result<T>;
+        }
+
+        impl<T, E> $ext<T, E> for Result<T, E>
+        where
+            Result<T, E>: $krate::$context_trait<T, E>,
+        {
+            fn $method(self, status: StatusCode) -> $krate::Result<T> {
+                $krate::$context_trait::$context_fn(self, StatusHint(status))
+            }
+        }
+
+        impl From<$krate::$report> for ApiError {
+            fn from(error: $krate::$report) -> Self {
+                // Only an ApiError without any context is returned as it is
+                let root: &(dyn Error + 'static) = &*error;
+                if root.is::<ApiError>() {
+                    return error.downcast().expect("checked to be an ApiError");
+                }
+
+                let hint = error.downcast_ref::<StatusHint>().copied();
+                crate::status_mapper::api_error_from_report(error, hint)
+            }
+        }
+    };
+}
@@ -0,0 +1,310 @@
+//! Reporting of server errors to error tracking systems.
+//!
+//! Whenever an [ApiError] with a server error status (`5xx`) is converted
+//! into a response of one of the supported web frameworks, it is handed to
+//! an [ErrorReporter] before the response is generated. The reporter sees
+//! the complete [ApiError] including its source chain, its [Extensions](http::Extensions)
+//! and its backtrace.
+//!
+//! A reporter can be registered globally with [set_global_error_reporter].
+//! An application which needs a different reporter can attach a [ReportTo]
+//! extension to its errors which takes precedence over the global one.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let reporter = InMemoryReporter::default();
+//!
+//! let error = ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+//!     .message("the database is on fire")
+//!     .extension(ReportTo::new(reporter.clone()))
+//!     .finish();
+//!
+//! report_server_error(&error);
+//!
+//! let reports = reporter.reports();
+//! assert_eq!(reports.len(), 1);
+//! assert_eq!(reports[0].status, StatusCode::INTERNAL_SERVER_ERROR);
+//! assert_eq!(reports[0].message.as_deref(), Some("the database is on fire"));
+//! ```
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use super::*;
+
+/// Receives [ApiError]s with a server error status right before
+/// they are turned into a response.
+///
+/// Implemented for all closures `Fn(&ApiError) + Send + Sync`.
+pub trait ErrorReporter: Send + Sync {
+    /// Report the given error.
+    ///
+    /// This is called synchronously while the response is generated
+    /// so implementations should hand off expensive work.
+    fn report(&self, error: &ApiError);
+}
+
+impl<F> ErrorReporter for F
+where
+    F: Fn(&ApiError) + Send + Sync,
+{
+    fn report(&self, error: &ApiError) {
+        self(error)
+    }
+}
+
+impl<R: ErrorReporter + ?Sized> ErrorReporter for Arc<R> {
+    fn report(&self, error: &ApiError) {
+        (**self).report(error)
+    }
+}
+
+static GLOBAL_REPORTER: RwLock<Option<Arc<dyn ErrorReporter>>> = RwLock::new(None);
+
+/// Registers the [ErrorReporter] used for all errors which do not
+/// carry a [ReportTo] extension.
+///
+/// An already registered reporter will be replaced.
+pub fn set_global_error_reporter<R: ErrorReporter + 'static>(reporter: R) {
+    let mut global = GLOBAL_REPORTER
+        .write()
+        .unwrap_or_else(|err| err.into_inner());
+    *global = Some(Arc::new(reporter));
+}
+
+/// Removes the globally registered [ErrorReporter].
+pub fn clear_global_error_reporter() {
+    let mut global = GLOBAL_REPORTER
+        .write()
+        .unwrap_or_else(|err| err.into_inner());
+    *global = None;
+}
+
+/// Hands the error to the responsible [ErrorReporter] if its status
+/// is a server error.
+///
+/// A [ReportTo] extension on the error takes precedence over the
+/// globally registered reporter. If there is no reporter nothing happens.
+///
+/// This is invoked by all conversions of an [ApiError] into a response
+/// of a web framework.
+pub fn report_server_error(error: &ApiError) {
+    if !error.status().is_server_error() {
+        return;
+    }
+
+    if let Some(report_to) = error.extensions().get::<ReportTo>() {
+        report_to.0.report(error);
+        return;
+    }
+
+    let global = GLOBAL_REPORTER
+        .read()
+        .unwrap_or_else(|err| err.into_inner())
+        .clone();
+
+    if let Some(reporter) = global {
+        reporter.report(error);
+    }
+}
+
+/// An extension for an [ApiError] which routes it to a specific
+/// [ErrorReporter] instead of the global one.
+///
+/// This allows applications which share a process to report to
+/// different destinations.
+#[derive(Clone)]
+pub struct ReportTo(pub Arc<dyn ErrorReporter>);
+
+impl ReportTo {
+    pub fn new<R: ErrorReporter + 'static>(reporter: R) -> Self {
+        Self(Arc::new(reporter))
+    }
+}
+
+impl fmt::Debug for ReportTo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ReportTo")
+    }
+}
+
+/// An [ErrorReporter] which only forwards every n-th error
+/// to the wrapped reporter.
+///
+/// The first error is always forwarded.
+pub struct Sampled<R> {
+    inner: R,
+    one_in: u64,
+    counter: AtomicU64,
+}
+
+impl<R: ErrorReporter> Sampled<R> {
+    /// Forward one out of `n` errors.
+    ///
+    /// A value of `0` is treated like `1` which forwards every error.
+    pub fn one_in(inner: R, n: u64) -> Self {
+        Self {
+            inner,
+            one_in: n.max(1),
+            counter: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<R: ErrorReporter> ErrorReporter for Sampled<R> {
+    fn report(&self, error: &ApiError) {
+        let position = self
+            .counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |position| {
+                Some((position + 1) % self.one_in)
+            })
+            .unwrap_or_default();
+
+        if position == 0 {
+            self.inner.report(error)
+        }
+    }
+}
+
+type FingerprintFn = dyn Fn(&ApiError) -> String + Send + Sync;
+
+/// An [ErrorReporter] which forwards errors with the same fingerprint
+/// only once within a given time window.
+///
+/// By default the [fingerprint](ApiError::fingerprint) of the error is used.
+/// It does not tell apart errors with boxed sources of different types,
+/// e.g. errors created from an `anyhow::Error`. Use
+/// [Deduplicated::fingerprint_with] if such errors must not be suppressed.
+pub struct Deduplicated<R> {
+    inner: R,
+    window: Duration,
+    fingerprint: Box<FingerprintFn>,
+    last_seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl<R: ErrorReporter> Deduplicated<R> {
+    /// Forward errors with the same fingerprint at most once per `window`.
+    pub fn new(inner: R, window: Duration) -> Self {
+        Self {
+            inner,
+            window,
+            fingerprint: Box::new(ApiError::fingerprint),
+            last_seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Use a custom function to compute the fingerprint of an error.
+    pub fn fingerprint_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&ApiError) -> String + Send + Sync + 'static,
+    {
+        self.fingerprint = Box::new(f);
+        self
+    }
+}
+
+impl<R: ErrorReporter> ErrorReporter for Deduplicated<R> {
+    fn report(&self, error: &ApiError) {
+        let fingerprint = (self.fingerprint)(error);
+        let now = Instant::now();
+
+        let forward = {
+            let mut last_seen = self.last_seen.lock().unwrap_or_else(|err| err.into_inner());
+            last_seen.retain(|_, seen| now.duration_since(*seen) < self.window);
+
+            match last_seen.entry(fingerprint) {
+                Entry::Occupied(_) => false,
+                Entry::Vacant(entry) => {
+                    entry.insert(now);
+                    true
+                }
+            }
+        };
+
+        if forward {
+            self.inner.report(error)
+        }
+    }
+}
+
+/// A snapshot of an [ApiError] taken by the [InMemoryReporter].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportedError {
+    pub status: StatusCode,
+    pub title: Option<String>,
+    pub message: Option<String>,
+    pub type_url: Option<String>,
+    pub instance: Option<String>,
+    pub fields: FieldMap,
+    /// The stringified source errors starting with the direct source
+    pub source_chain: Vec<String>,
+}
+
+impl From<&ApiError> for ReportedError {
+    fn from(error: &ApiError) -> Self {
+        let mut source_chain = Vec::new();
+        let mut next = error.source();
+        while let Some(source) = next {
+            source_chain.push(source.to_string());
+            next = source.source();
+        }
+
+        Self {
+            status: error.status(),
+            title: error.title().map(ToOwned::to_owned),
+            message: error.message().map(ToOwned::to_owned),
+            type_url: error.type_url().map(ToOwned::to_owned),
+            instance: error.instance().map(ToOwned::to_owned),
+            fields: error.fields().clone(),
+            source_chain,
+        }
+    }
+}
+
+/// An [ErrorReporter] which keeps all reported errors in memory.
+///
+/// Meant to be used in tests. Clones share the same storage so a
+/// clone can be registered while the original is used for assertions.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryReporter {
+    reports: Arc<Mutex<Vec<ReportedError>>>,
+}
+
+impl InMemoryReporter {
+    /// Returns all errors reported so far
+    pub fn reports(&self) -> Vec<ReportedError> {
+        self.lock().clone()
+    }
+
+    /// Returns the number of reported errors
+    pub fn len(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Returns `true` if nothing has been reported yet
+    pub fn is_empty(&self) -> bool {
+        self.lock().is_empty()
+    }
+
+    /// Removes all reported errors
+    pub fn clear(&self) {
+        self.lock().clear()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ReportedError>> {
+        self.reports.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl ErrorReporter for InMemoryReporter {
+    fn report(&self, error: &ApiError) {
+        self.lock().push(error.into())
+    }
+}
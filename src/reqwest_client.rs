@@ -0,0 +1,116 @@
+//! Integration with [reqwest] clients.
+//!
+//! Requires the `reqwest` feature
+use std::error::Error;
+use std::fmt;
+use std::future::Future;
+
+use super::*;
+
+/// Extension trait for [reqwest::Response]
+///
+/// Requires the `reqwest` feature
+pub trait ReqwestResponseExt: Sized {
+    /// Turns a response with a status other than `2xx` into an error
+    /// containing the problem sent by the server.
+    ///
+    /// This mirrors [reqwest::Response::error_for_status] but consumes
+    /// the body of the response to extract the problem.
+    /// At most [DEFAULT_PROBLEM_BODY_LIMIT] bytes of the body are collected.
+    /// See [ReqwestResponseExt::error_for_problem_with_limit].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use http_api_problem::*;
+    ///
+    /// async fn fetch_user() -> Result<String, ClientProblemError> {
+    ///     let response = reqwest::get("http://localhost:8080/users/1")
+    ///         .await?
+    ///         .error_for_problem()
+    ///         .await?;
+    ///
+    ///     Ok(response.text().await?)
+    /// }
+    /// ```
+    fn error_for_problem(
+        self,
+    ) -> impl Future<Output = Result<reqwest::Response, ClientProblemError>> + Send {
+        self.error_for_problem_with_limit(DEFAULT_PROBLEM_BODY_LIMIT)
+    }
+
+    /// Turns a response with a status other than `2xx` into an error
+    /// containing the problem sent by the server.
+    ///
+    /// The body of a successful response is left untouched. Otherwise at most
+    /// `limit` bytes of the body are collected to extract the problem.
+    /// A larger body results in a [ClientProblemError::Transport].
+    /// See [HttpApiProblem::from_http_response] on how the problem is
+    /// extracted.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use http_api_problem::*;
+    ///
+    /// async fn fetch_user() -> Result<String, ClientProblemError> {
+    ///     let response = reqwest::get("http://localhost:8080/users/1")
+    ///         .await?
+    ///         .error_for_problem_with_limit(1024)
+    ///         .await?;
+    ///
+    ///     Ok(response.text().await?)
+    /// }
+    /// ```
+    fn error_for_problem_with_limit(
+        self,
+        limit: usize,
+    ) -> impl Future<Output = Result<reqwest::Response, ClientProblemError>> + Send;
+}
+
+impl ReqwestResponseExt for reqwest::Response {
+    async fn error_for_problem_with_limit(
+        mut self,
+        limit: usize,
+    ) -> Result<reqwest::Response, ClientProblemError> {
+        if self.status().is_success() {
+            return Ok(self);
+        }
+
+        let status = self.status();
+        let headers = self.headers().clone();
+        let mut body = Vec::new();
+        while let Some(chunk) = self.chunk().await? {
+            if body.len() + chunk.len() > limit {
+                return Err(ClientProblemError::Transport(Box::new(BodyTooLarge {
+                    limit,
+                })));
+            }
+            body.extend_from_slice(&chunk);
+        }
+
+        Err(ClientProblemError::Problem(Box::new(
+            ProblemResponse::from_parts(status, headers, &body),
+        )))
+    }
+}
+
+/// The body of a response exceeded the limit
+#[derive(Debug)]
+struct BodyTooLarge {
+    limit: usize,
+}
+
+impl fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "body exceeds the limit of {} bytes", self.limit)
+    }
+}
+
+impl Error for BodyTooLarge {}
+
+impl From<reqwest::Error> for ClientProblemError {
+    fn from(err: reqwest::Error) -> Self {
+        ClientProblemError::Transport(err.into())
+    }
+}
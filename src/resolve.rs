@@ -0,0 +1,86 @@
+//! Resolution of relative `type` and `instance` URIs.
+//!
+//! [RFC 7807](https://tools.ietf.org/html/rfc7807#section-3.1) allows `type`
+//! and `instance` to be relative URI references which have to be resolved
+//! against the base URI of the document. For a problem received in a response
+//! that is usually the URL of the request.
+//!
+//! Requires the `url` feature
+use url::Url;
+
+use super::*;
+
+impl HttpApiProblem {
+    /// Resolves relative `type` and `instance` URI references against `base`
+    /// so that they become absolute URIs.
+    ///
+    /// Members which are already absolute, i.e. which start with a scheme
+    /// like `https:` or `about:`, stay untouched. If one of the members can
+    /// not be resolved nothing is changed and an error is returned.
+    ///
+    /// Requires the `url` feature
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    /// use url::Url;
+    ///
+    /// let mut p = HttpApiProblem::new(StatusCode::FORBIDDEN)
+    ///     .type_url("/problems/out-of-credit")
+    ///     .instance("msgs/abc");
+    ///
+    /// p.resolve_against(&Url::parse("https://example.com/account/12345/").unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some("https://example.com/problems/out-of-credit"), p.type_url.as_deref());
+    /// assert_eq!(Some("https://example.com/account/12345/msgs/abc"), p.instance.as_deref());
+    /// ```
+    pub fn resolve_against(&mut self, base: &Url) -> Result<(), url::ParseError> {
+        let type_url = resolve(base, self.type_url.as_deref())?;
+        let instance = resolve(base, self.instance.as_deref())?;
+
+        self.type_url = type_url;
+        self.instance = instance;
+
+        Ok(())
+    }
+
+    /// Resolves relative `type` and `instance` URI references of a problem
+    /// received in a response.
+    ///
+    /// The base URI is the URL of the request, after following redirects.
+    /// The `Content-Location` header of the response does not change the
+    /// base URI ([RFC 9110, Section 8.7](https://www.rfc-editor.org/rfc/rfc9110#section-8.7)).
+    ///
+    /// Requires the `url` feature
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use http_api_problem::*;
+    /// use url::Url;
+    ///
+    /// let mut p = HttpApiProblem::new(StatusCode::FORBIDDEN).type_url("out-of-credit");
+    ///
+    /// p.resolve_for_response(&Url::parse("https://example.com/api/orders").unwrap())
+    ///     .unwrap();
+    ///
+    /// assert_eq!(Some("https://example.com/api/out-of-credit"), p.type_url.as_deref());
+    /// ```
+    pub fn resolve_for_response(&mut self, request_url: &Url) -> Result<(), url::ParseError> {
+        self.resolve_against(request_url)
+    }
+}
+
+fn resolve(base: &Url, reference: Option<&str>) -> Result<Option<String>, url::ParseError> {
+    reference
+        .map(|reference| {
+            if conformance::has_scheme(reference) {
+                Ok(reference.to_owned())
+            } else {
+                base.join(reference).map(String::from)
+            }
+        })
+        .transpose()
+}
@@ -0,0 +1,148 @@
+//! Extensions of [Result] and [Option] to create [ApiError]s.
+//!
+//! The methods of [ApiResultExt] and [ApiOptionExt] replace closures
+//! passed to `map_err` which build an [ApiError] with the error as the source.
+//! An error which already is an [ApiError] is not wrapped but reused.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::io;
+//! use http_api_problem::*;
+//!
+//! fn read_profile(id: u32) -> Result<String, io::Error> {
+//!     Err(io::Error::other("connection lost"))
+//! }
+//!
+//! let err = read_profile(42)
+//!     .or_status(StatusCode::SERVICE_UNAVAILABLE)
+//!     .unwrap_err();
+//!
+//! assert_eq!(err.status(), StatusCode::SERVICE_UNAVAILABLE);
+//! assert_eq!(err.detail_message().as_deref(), Some("connection lost"));
+//!
+//! let users = ["anna", "bob"];
+//! let err = users.get(5).ok_or_not_found().unwrap_err();
+//!
+//! assert_eq!(err.status(), StatusCode::NOT_FOUND);
+//! ```
+use std::any::TypeId;
+use std::error::Error;
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::*;
+
+/// Turns the error of a [Result] into an [ApiError] with the
+/// original error as the source.
+// Handlers return an `ApiError` by value
+#[allow(clippy::result_large_err)]
+pub trait ApiResultExt<T, E> {
+    /// Turns the error into an [ApiError] with the given status
+    ///
+    /// An [ApiError] is returned unchanged.
+    fn or_status(self, status: StatusCode) -> Result<T, ApiError>;
+
+    /// Turns the error into a `404 - Not Found` with the message
+    /// "`what` not found"
+    ///
+    /// An [ApiError] is returned unchanged.
+    fn or_not_found<W: Display>(self, what: W) -> Result<T, ApiError>;
+
+    /// Turns the error into the [ApiError] built by `f`
+    ///
+    /// The original error becomes the source.
+    fn with_problem<F>(self, f: F) -> Result<T, ApiError>
+    where
+        F: FnOnce(&E) -> ApiErrorBuilder;
+
+    /// Adds a field to the [ApiError] the error is turned into
+    ///
+    /// The status of an error which is not an [ApiError] is determined
+    /// by the global [StatusMapper] like with [ApiError::from_error].
+    fn context_field<K: Into<String>, V: Serialize>(self, name: K, value: V)
+        -> Result<T, ApiError>;
+}
+
+impl<T, E> ApiResultExt<T, E> for Result<T, E>
+where
+    E: Error + Send + Sync + 'static,
+{
+    fn or_status(self, status: StatusCode) -> Result<T, ApiError> {
+        self.map_err(|err| {
+            into_api_error_or(err, |err| ApiError::builder(status).source(err).finish())
+        })
+    }
+
+    fn or_not_found<W: Display>(self, what: W) -> Result<T, ApiError> {
+        self.map_err(|err| {
+            into_api_error_or(err, |err| {
+                ApiError::builder(StatusCode::NOT_FOUND)
+                    .message(format!("{} not found", what))
+                    .source(err)
+                    .finish()
+            })
+        })
+    }
+
+    fn with_problem<F>(self, f: F) -> Result<T, ApiError>
+    where
+        F: FnOnce(&E) -> ApiErrorBuilder,
+    {
+        self.map_err(|err| f(&err).source(err).finish())
+    }
+
+    fn context_field<K: Into<String>, V: Serialize>(
+        self,
+        name: K,
+        value: V,
+    ) -> Result<T, ApiError> {
+        self.map_err(|err| {
+            let mut api_error =
+                into_api_error_or(err, crate::status_mapper::api_error_from_typed_error);
+            api_error.add_field(name, value);
+            api_error
+        })
+    }
+}
+
+/// Returns an [ApiError] as it is and builds a new one for other errors
+///
+/// Only an [ApiError] is boxed to get it out of the generic `E`. Other
+/// errors are handed to `f` as they are so that their type is kept as the
+/// type of the source.
+fn into_api_error_or<E, F>(err: E, f: F) -> ApiError
+where
+    E: Error + Send + Sync + 'static,
+    F: FnOnce(E) -> ApiError,
+{
+    if TypeId::of::<E>() != TypeId::of::<ApiError>() {
+        return f(err);
+    }
+
+    let boxed: Box<dyn Error + Send + Sync + 'static> = Box::new(err);
+    *boxed
+        .downcast::<ApiError>()
+        .expect("checked to be an ApiError")
+}
+
+/// Turns a `None` into an [ApiError]
+#[allow(clippy::result_large_err)]
+pub trait ApiOptionExt<T> {
+    /// Turns a `None` into a `404 - Not Found`
+    fn ok_or_not_found(self) -> Result<T, ApiError>;
+
+    /// Turns a `None` into an [ApiError] with the given status
+    fn ok_or_status(self, status: StatusCode) -> Result<T, ApiError>;
+}
+
+impl<T> ApiOptionExt<T> for Option<T> {
+    fn ok_or_not_found(self) -> Result<T, ApiError> {
+        self.ok_or_status(StatusCode::NOT_FOUND)
+    }
+
+    fn ok_or_status(self, status: StatusCode) -> Result<T, ApiError> {
+        self.ok_or_else(|| ApiError::new(status))
+    }
+}
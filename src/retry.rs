@@ -0,0 +1,286 @@
+//! Advice for clients whether a failed request should be retried.
+//!
+//! The advice is derived from the status of a problem, a `Retry-After` header
+//! and the additional fields `retry_after` (a number of seconds) and `retryable`
+//! (a boolean) which a server can use to communicate its intent explicitly.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::time::Duration;
+//! use http_api_problem::*;
+//!
+//! let p = HttpApiProblem::new(StatusCode::TOO_MANY_REQUESTS).value("retry_after", &30);
+//! assert_eq!(p.retry_advice(), RetryAdvice::Retry { after: Some(Duration::from_secs(30)) });
+//!
+//! let p = HttpApiProblem::new(StatusCode::BAD_REQUEST);
+//! assert_eq!(p.retry_advice(), RetryAdvice::DoNotRetry);
+//! ```
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::header::{HeaderMap, RETRY_AFTER};
+
+use super::*;
+
+/// Whether and when a failed request should be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAdvice {
+    /// The request may be retried, optionally after the given delay
+    Retry { after: Option<Duration> },
+    /// Retrying the request will not succeed
+    DoNotRetry,
+}
+
+impl RetryAdvice {
+    /// Returns `true` if the request may be retried
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, RetryAdvice::Retry { .. })
+    }
+
+    /// Returns the delay to wait for before retrying if one is known
+    pub fn delay(&self) -> Option<Duration> {
+        match self {
+            RetryAdvice::Retry { after } => *after,
+            RetryAdvice::DoNotRetry => None,
+        }
+    }
+}
+
+impl HttpApiProblem {
+    /// Returns whether the request which caused this problem may be retried.
+    ///
+    /// The additional field `retryable` decides if present. Otherwise a problem
+    /// is retryable if it has a `retry_after` field or one of the statuses
+    /// `408`, `429`, `502`, `503` or `504`. The delay is taken from `retry_after`.
+    pub fn retry_advice(&self) -> RetryAdvice {
+        self.advise(self.retry_after_field())
+    }
+
+    /// Like [HttpApiProblem::retry_advice] but a `Retry-After` header of the
+    /// response carrying this problem takes precedence over the `retry_after` field.
+    ///
+    /// #Example
+    ///
+    /// ```rust
+    /// use std::time::Duration;
+    /// use http::header::{HeaderMap, HeaderValue, RETRY_AFTER};
+    /// use http_api_problem::*;
+    ///
+    /// let mut headers = HeaderMap::new();
+    /// headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+    ///
+    /// let p = HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE);
+    ///
+    /// assert_eq!(
+    ///     p.retry_advice_with_headers(&headers).delay(),
+    ///     Some(Duration::from_secs(120))
+    /// );
+    /// ```
+    pub fn retry_advice_with_headers(&self, headers: &HeaderMap) -> RetryAdvice {
+        let header = headers
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, SystemTime::now()));
+
+        self.advise(header.or_else(|| self.retry_after_field()))
+    }
+
+    fn advise(&self, after: Option<Duration>) -> RetryAdvice {
+        let retryable = match self.json_value("retryable").and_then(Value::as_bool) {
+            Some(retryable) => retryable,
+            None => after.is_some() || self.status.is_some_and(is_retryable_status),
+        };
+
+        if retryable {
+            RetryAdvice::Retry { after }
+        } else {
+            RetryAdvice::DoNotRetry
+        }
+    }
+
+    fn retry_after_field(&self) -> Option<Duration> {
+        let secs = match self.json_value("retry_after")? {
+            Value::Number(n) => n.as_f64()?,
+            Value::String(s) => s.trim().parse().ok()?,
+            _ => return None,
+        };
+
+        Duration::try_from_secs_f64(secs).ok()
+    }
+}
+
+fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Parses the value of a `Retry-After` header.
+///
+/// The value is either a number of seconds or an HTTP date
+/// (`Sun, 06 Nov 1994 08:49:37 GMT`) which is turned into a delay relative
+/// to `now`. A date in the past results in a delay of zero.
+///
+/// As required by [RFC 9110, Section 5.6.7](https://www.rfc-editor.org/rfc/rfc9110#section-5.6.7)
+/// the obsolete formats of RFC 850 (`Sunday, 06-Nov-94 08:49:37 GMT`) and
+/// of ANSI C's `asctime()` (`Sun Nov  6 08:49:37 1994`) are accepted as well.
+/// A two-digit year is taken to be at most 50 years in the future. Dates
+/// which do not exist, like `31 Feb`, are rejected.
+///
+/// #Example
+///
+/// ```rust
+/// use std::time::{Duration, SystemTime, UNIX_EPOCH};
+/// use http_api_problem::*;
+///
+/// let now = UNIX_EPOCH + Duration::from_secs(784111717);
+///
+/// assert_eq!(parse_retry_after("60", now), Some(Duration::from_secs(60)));
+/// assert_eq!(
+///     parse_retry_after("Sun, 06 Nov 1994 08:49:37 GMT", now),
+///     Some(Duration::from_secs(60))
+/// );
+/// assert_eq!(parse_retry_after("Sun, 31 Feb 1994 08:49:37 GMT", now), None);
+/// ```
+pub fn parse_retry_after(value: &str, now: SystemTime) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = UNIX_EPOCH + Duration::from_secs(parse_http_date(value, now)?);
+    Some(at.duration_since(now).unwrap_or_default())
+}
+
+const DAY_NAMES: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+const LONG_DAY_NAMES: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+const MONTH_NAMES: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Parses an HTTP date in one of the three formats of RFC 9110 into
+/// seconds since the epoch
+///
+/// `now` is needed to determine the century of a two-digit year.
+fn parse_http_date(value: &str, now: SystemTime) -> Option<u64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    match parts.as_slice() {
+        // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+        [day_name, day, month, year, time, "GMT"] => {
+            if !DAY_NAMES.contains(&day_name.strip_suffix(',')?) {
+                return None;
+            }
+            seconds_since_epoch(parse_digits(year, 4..=4)?, month, day, time)
+        }
+        // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+        [day_name, date, time, "GMT"] => {
+            let [day, month, year] = date.split('-').collect::<Vec<_>>()[..] else {
+                return None;
+            };
+            if !LONG_DAY_NAMES.contains(&day_name.strip_suffix(',')?) {
+                return None;
+            }
+            let year = full_year(parse_digits(year, 2..=2)?, now);
+            seconds_since_epoch(year, month, day, time)
+        }
+        // asctime: Sun Nov  6 08:49:37 1994
+        [day_name, month, day, time, year] => {
+            if !DAY_NAMES.contains(day_name) {
+                return None;
+            }
+            seconds_since_epoch(parse_digits(year, 4..=4)?, month, day, time)
+        }
+        _ => None,
+    }
+}
+
+/// Turns a two-digit year into the year at most 50 years after
+/// and less than 50 years before the year of `now`
+fn full_year(two_digits: u64, now: SystemTime) -> u64 {
+    // Seconds of an average year of the Gregorian calendar
+    const YEAR: u64 = 31_556_952;
+
+    let now = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+    let current = 1970 + now / YEAR;
+    let year = current - current % 100 + two_digits;
+
+    if year > current + 50 {
+        year - 100
+    } else if year + 50 <= current {
+        year + 100
+    } else {
+        year
+    }
+}
+
+/// Validates the components of a date and returns the seconds since the epoch
+fn seconds_since_epoch(year: u64, month: &str, day: &str, time: &str) -> Option<u64> {
+    let month = MONTH_NAMES.iter().position(|m| *m == month)? as u64 + 1;
+    let day = parse_digits(day, 1..=2)?;
+    if year < 1970 || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+
+    let mut time = time.split(':').map(|part| parse_digits(part, 2..=2));
+    let (hours, minutes, seconds) = (time.next()??, time.next()??, time.next()??);
+    // A second of 60 is a leap second
+    if time.next().is_some() || hours > 23 || minutes > 59 || seconds > 60 {
+        return None;
+    }
+
+    // Days since the epoch of a date in the proleptic Gregorian calendar
+    let (y, m) = if month <= 2 {
+        (year - 1, month + 9)
+    } else {
+        (year, month - 3)
+    };
+    let era = y / 400;
+    let year_of_era = y % 400;
+    let day_of_year = (153 * m + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = (era * 146_097 + day_of_era).checked_sub(719_468)?;
+
+    Some(days * 86_400 + hours * 3_600 + minutes * 60 + seconds)
+}
+
+/// Parses a number consisting of only ASCII digits
+fn parse_digits(value: &str, len: std::ops::RangeInclusive<usize>) -> Option<u64> {
+    if !len.contains(&value.len()) || !value.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    value.parse().ok()
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+fn is_leap_year(year: u64) -> bool {
+    match (year % 4, year % 100, year % 400) {
+        (_, _, 0) => true,
+        (_, 0, _) => false,
+        (0, _, _) => true,
+        _ => false,
+    }
+}
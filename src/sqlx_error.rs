@@ -0,0 +1,174 @@
+//! Mapping of [sqlx] errors to problems.
+//!
+//! `From<sqlx::Error> for ApiError` classifies database errors:
+//!
+//! | Error | Status |
+//! |-------|--------|
+//! | `RowNotFound` | `404` |
+//! | unique violation | `409` |
+//! | foreign key violation | `409` or `422` |
+//! | check violation | `422` |
+//! | `PoolTimedOut` | `503` |
+//! | all others | `500` |
+//!
+//! A foreign key violation becomes a `422 - Unprocessable Entity` if the
+//! database reports that a referenced record does not exist and a
+//! `409 - Conflict` otherwise, e.g. if a record which is still referenced
+//! is deleted. How the cases are told apart depends on the database:
+//!
+//! * MySQL: by the error number (`1452` vs. `1451`). Requires the
+//!   `sqlx-mysql` feature which enables the MySQL driver of `sqlx`.
+//!   Without it a foreign key violation always results in a `409`
+//! * PostgreSQL: by the message. This only works if the server sends
+//!   English messages (`lc_messages`)
+//! * SQLite does not tell the cases apart and always results in a `409`
+//!
+//! Neither the message of the database nor the name of the violated
+//! constraint is exposed by default since both may reveal the schema.
+//! [set_sqlx_constraint_exposure] exposes the name of the constraint as
+//! a member of the problem.
+//!
+//! The original error is always attached as the source.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let err: ApiError = sqlx::Error::RowNotFound.into();
+//! assert_eq!(err.status(), StatusCode::NOT_FOUND);
+//! ```
+use std::borrow::Cow;
+use std::sync::RwLock;
+
+use sqlx::error::{DatabaseError, ErrorKind};
+
+use super::*;
+
+/// The suggested member to expose the name of a violated constraint as
+pub const CONSTRAINT_FIELD: &str = "constraint";
+
+static CONSTRAINT_EXPOSURE: RwLock<Option<ConstraintExposure>> = RwLock::new(None);
+
+/// Whether the name of a violated constraint becomes part of the problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintExposure {
+    /// The name is exposed as the given member
+    Member(Cow<'static, str>),
+    /// The name is not exposed
+    Hide,
+}
+
+impl Default for ConstraintExposure {
+    /// Hides the name
+    fn default() -> Self {
+        ConstraintExposure::Hide
+    }
+}
+
+/// Configures how the names of violated constraints are exposed
+pub fn set_sqlx_constraint_exposure(exposure: ConstraintExposure) {
+    let mut guard = CONSTRAINT_EXPOSURE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(exposure);
+}
+
+/// Restores the default [ConstraintExposure]
+pub fn clear_sqlx_constraint_exposure() {
+    let mut guard = CONSTRAINT_EXPOSURE
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = None;
+}
+
+fn constraint_exposure() -> ConstraintExposure {
+    CONSTRAINT_EXPOSURE
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+        .unwrap_or_default()
+}
+
+/// Returns the status a [sqlx::Error] is mapped to
+pub fn sqlx_error_status(error: &sqlx::Error) -> StatusCode {
+    match error {
+        sqlx::Error::RowNotFound => StatusCode::NOT_FOUND,
+        sqlx::Error::PoolTimedOut => StatusCode::SERVICE_UNAVAILABLE,
+        sqlx::Error::Database(db_error) => match db_error.kind() {
+            ErrorKind::UniqueViolation => StatusCode::CONFLICT,
+            ErrorKind::ForeignKeyViolation if references_missing_record(&**db_error) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            ErrorKind::ForeignKeyViolation => StatusCode::CONFLICT,
+            ErrorKind::CheckViolation => StatusCode::UNPROCESSABLE_ENTITY,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+/// Distinguishes inserting a dangling reference from deleting a
+/// referenced record
+///
+/// MySQL errors are told apart by their number if the `sqlx-mysql` feature
+/// is enabled. PostgreSQL errors are told apart by their English message.
+/// SQLite does not tell the cases apart.
+fn references_missing_record(error: &dyn DatabaseError) -> bool {
+    #[cfg(feature = "sqlx-mysql")]
+    if let Some(error) = error.try_downcast_ref::<sqlx::mysql::MySqlDatabaseError>() {
+        // ER_NO_REFERENCED_ROW and ER_NO_REFERENCED_ROW_2
+        return matches!(error.number(), 1216 | 1452);
+    }
+
+    error.message().starts_with("insert or update on table")
+}
+
+/// Returns the name of the violated constraint
+///
+/// SQLite does not report the name. For SQLite the part of the message
+/// following "constraint failed: " is used.
+fn constraint_name(error: &dyn DatabaseError) -> Option<&str> {
+    error.constraint().or_else(|| {
+        error
+            .message()
+            .split_once("constraint failed: ")
+            .map(|(_, name)| name)
+    })
+}
+
+impl From<sqlx::Error> for ApiError {
+    /// The status is determined by [sqlx_error_status]
+    fn from(error: sqlx::Error) -> Self {
+        let status = sqlx_error_status(&error);
+
+        let message = match &error {
+            sqlx::Error::RowNotFound => "The requested record does not exist",
+            sqlx::Error::PoolTimedOut => "The database is currently not available",
+            sqlx::Error::Database(db_error) => match db_error.kind() {
+                ErrorKind::UniqueViolation => "A record with the same unique values already exists",
+                ErrorKind::ForeignKeyViolation if status == StatusCode::CONFLICT => {
+                    "The record is referenced by or references other records"
+                }
+                ErrorKind::ForeignKeyViolation => "A referenced record does not exist",
+                ErrorKind::CheckViolation => "The record violates a check constraint",
+                _ => "A database error occurred",
+            },
+            _ => "A database error occurred",
+        };
+
+        let mut builder = ApiError::builder(status).message(message);
+
+        if status != StatusCode::INTERNAL_SERVER_ERROR {
+            if let sqlx::Error::Database(db_error) = &error {
+                if let (ConstraintExposure::Member(member), Some(name)) =
+                    (constraint_exposure(), constraint_name(&**db_error))
+                {
+                    builder = builder.field(member, name);
+                }
+            }
+        }
+
+        builder.source(error).finish()
+    }
+}
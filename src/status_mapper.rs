@@ -0,0 +1,329 @@
+//! Central classification of errors of foreign types.
+//!
+//! A [StatusMapper] holds rules which map errors to a status, a title and
+//! a type. An [ApiError] created from a boxed error consults the mapper
+//! installed with [set_global_status_mapper] and walks the source chain of
+//! the error until a rule matches.
+//!
+//! Independent of the rules an [ApiError] in the chain keeps its status,
+//! title and type, a [StatusHint] determines the status and an
+//! [std::io::Error] is mapped like in `From<io::Error> for ApiError`. Errors which are not matched at all
+//! become a `500 - Internal Server Error`.
+//!
+//! # Example
+//!
+//! ```rust
+//! use std::error::Error;
+//! use std::fmt;
+//! use http_api_problem::*;
+//!
+//! #[derive(Debug)]
+//! struct LockTimeout;
+//!
+//! impl fmt::Display for LockTimeout {
+//!     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+//!         write!(f, "could not acquire lock")
+//!     }
+//! }
+//!
+//! impl Error for LockTimeout {}
+//!
+//! let mapper = StatusMapper::new().map_type::<LockTimeout, _>(
+//!     StatusMapping::new(StatusCode::SERVICE_UNAVAILABLE).title("Busy"),
+//! );
+//!
+//! let err = mapper.to_api_error(Box::new(LockTimeout));
+//!
+//! assert_eq!(err.status(), StatusCode::SERVICE_UNAVAILABLE);
+//! assert_eq!(err.title(), Some("Busy"));
+//! assert_eq!(err.detail_message().as_deref(), Some("could not acquire lock"));
+//! ```
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::sync::{Arc, RwLock};
+
+use super::*;
+
+type BoxError = Box<dyn Error + Send + Sync + 'static>;
+
+type Rule = Box<dyn Fn(&(dyn Error + 'static)) -> Option<StatusMapping> + Send + Sync>;
+
+static GLOBAL_STATUS_MAPPER: RwLock<Option<Arc<StatusMapper>>> = RwLock::new(None);
+
+/// The status, title and type an error is mapped to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusMapping {
+    pub status: StatusCode,
+    pub title: Option<String>,
+    pub type_url: Option<String>,
+}
+
+impl StatusMapping {
+    /// Creates a mapping to the given status without a title or type
+    pub fn new<T: Into<StatusCode>>(status: T) -> Self {
+        Self {
+            status: status.into(),
+            title: None,
+            type_url: None,
+        }
+    }
+
+    /// Sets the title
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the type
+    pub fn type_url<T: Into<String>>(mut self, type_url: T) -> Self {
+        self.type_url = Some(type_url.into());
+        self
+    }
+}
+
+impl From<StatusCode> for StatusMapping {
+    fn from(status: StatusCode) -> Self {
+        Self::new(status)
+    }
+}
+
+/// A status attached to an error, e.g. as the context of an `anyhow::Error`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusHint(pub StatusCode);
+
+impl fmt::Display for StatusHint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for StatusHint {}
+
+/// Rules which map errors to a [StatusMapping]
+///
+/// The rules are tried in the order they were added.
+#[derive(Default)]
+pub struct StatusMapper {
+    rules: Vec<Rule>,
+}
+
+impl StatusMapper {
+    /// Creates a mapper without any rules
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maps all errors of type `E`
+    pub fn map_type<E, M>(self, mapping: M) -> Self
+    where
+        E: Error + 'static,
+        M: Into<StatusMapping>,
+    {
+        let mapping = mapping.into();
+        self.map_type_with(move |_: &E| Some(mapping.clone()))
+    }
+
+    /// Maps errors of type `E` with a function
+    ///
+    /// If the function returns `None` the next rule is tried.
+    pub fn map_type_with<E, F>(self, f: F) -> Self
+    where
+        E: Error + 'static,
+        F: Fn(&E) -> Option<StatusMapping> + Send + Sync + 'static,
+    {
+        self.map_with(move |error| error.downcast_ref::<E>().and_then(&f))
+    }
+
+    /// Maps all errors the predicate is true for
+    pub fn map_if<P, M>(self, predicate: P, mapping: M) -> Self
+    where
+        P: Fn(&(dyn Error + 'static)) -> bool + Send + Sync + 'static,
+        M: Into<StatusMapping>,
+    {
+        let mapping = mapping.into();
+        self.map_with(move |error| predicate(error).then(|| mapping.clone()))
+    }
+
+    /// Maps errors with a function
+    ///
+    /// If the function returns `None` the next rule is tried.
+    pub fn map_with<F>(mut self, f: F) -> Self
+    where
+        F: Fn(&(dyn Error + 'static)) -> Option<StatusMapping> + Send + Sync + 'static,
+    {
+        self.rules.push(Box::new(f));
+        self
+    }
+
+    /// Returns the mapping for an error
+    ///
+    /// The error and then its sources are checked one after the other.
+    /// For each of them the rules are tried before an [ApiError],
+    /// a [StatusHint] or an [io::Error] is recognized. With the `sqlx`
+    /// feature a `sqlx::Error` is recognized as well.
+    pub fn map(&self, error: &(dyn Error + 'static)) -> Option<StatusMapping> {
+        let mut current = Some(error);
+        while let Some(error) = current {
+            if let Some(mapping) = self.rules.iter().find_map(|rule| rule(error)) {
+                return Some(mapping);
+            }
+
+            if let Some(api_error) = error.downcast_ref::<ApiError>() {
+                return Some(StatusMapping {
+                    status: api_error.status(),
+                    title: api_error.title().map(str::to_owned),
+                    type_url: api_error.type_url().map(str::to_owned),
+                });
+            }
+
+            if let Some(hint) = error.downcast_ref::<StatusHint>() {
+                return Some(StatusMapping::new(hint.0));
+            }
+
+            if let Some(io_error) = error.downcast_ref::<io::Error>() {
+                return Some(StatusMapping::new(io_error_status(io_error)));
+            }
+
+            #[cfg(feature = "sqlx")]
+            if let Some(sqlx_error) = error.downcast_ref::<sqlx::Error>() {
+                return Some(StatusMapping::new(sqlx_error_status(sqlx_error)));
+            }
+
+            current = error.source();
+        }
+
+        None
+    }
+
+    /// Creates an [ApiError] with the error as the source
+    ///
+    /// An [ApiError] is returned as it is.
+    pub fn to_api_error(&self, error: BoxError) -> ApiError {
+        let error = match error.downcast::<ApiError>() {
+            Ok(api_error) => return *api_error,
+            Err(error) => error,
+        };
+
+        build_api_error(self.map(&*error), None, error)
+    }
+}
+
+fn build_api_error(
+    mapping: Option<StatusMapping>,
+    message: Option<String>,
+    source: BoxError,
+) -> ApiError {
+    mapped_builder(mapping, message)
+        .source_in_a_box(source)
+        .finish()
+}
+
+fn mapped_builder(mapping: Option<StatusMapping>, message: Option<String>) -> ApiErrorBuilder {
+    let mapping = mapping.unwrap_or_else(|| StatusMapping::new(StatusCode::INTERNAL_SERVER_ERROR));
+
+    let mut builder = ApiError::builder(mapping.status);
+    builder.title = mapping.title;
+    builder.type_url = mapping.type_url;
+    builder.message = message;
+    builder
+}
+
+/// Creates an [ApiError] with the error as the source using the global
+/// [StatusMapper]
+///
+/// Unlike [ApiError::from_error] the type of the source is kept for the
+/// [fingerprint](ApiError::fingerprint). The error must not be an [ApiError].
+pub(crate) fn api_error_from_typed_error<E>(error: E) -> ApiError
+where
+    E: Error + Send + Sync + 'static,
+{
+    let mapping = match global_status_mapper() {
+        Some(mapper) => mapper.map(&error),
+        None => StatusMapper::new().map(&error),
+    };
+
+    mapped_builder(mapping, None).source(error).finish()
+}
+
+fn global_status_mapper() -> Option<Arc<StatusMapper>> {
+    GLOBAL_STATUS_MAPPER
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Creates an [ApiError] from an error report like an `anyhow::Error`.
+///
+/// `hint` is the outermost [StatusHint] attached as context which can not
+/// be found by walking the source chain. It takes precedence over the global
+/// [StatusMapper]. The message skips the hint so that it does not show up
+/// as the `detail`.
+#[cfg(any(feature = "anyhow", feature = "eyre"))]
+pub(crate) fn api_error_from_report<R>(report: R, hint: Option<StatusHint>) -> ApiError
+where
+    R: Into<BoxError> + std::ops::Deref<Target = dyn Error + Send + Sync + 'static>,
+{
+    let root: &(dyn Error + 'static) = &*report;
+
+    let (mapping, message) = match hint {
+        Some(hint) => {
+            let hint_message = hint.to_string();
+            let mut current = Some(root);
+            let mut message = None;
+            while let Some(error) = current {
+                let error_message = error.to_string();
+                if error_message != hint_message {
+                    message = Some(error_message);
+                    break;
+                }
+                current = error.source();
+            }
+            (Some(StatusMapping::new(hint.0)), message)
+        }
+        None => {
+            let mapping = match global_status_mapper() {
+                Some(mapper) => mapper.map(root),
+                None => StatusMapper::new().map(root),
+            };
+            (mapping, None)
+        }
+    };
+
+    build_api_error(mapping, message, report.into())
+}
+
+/// Installs the [StatusMapper] used for the conversion of boxed errors
+/// into [ApiError]s.
+pub fn set_global_status_mapper(mapper: StatusMapper) {
+    let mut guard = GLOBAL_STATUS_MAPPER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(Arc::new(mapper));
+}
+
+/// Removes the global [StatusMapper]
+pub fn clear_global_status_mapper() {
+    let mut guard = GLOBAL_STATUS_MAPPER
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = None;
+}
+
+impl ApiError {
+    /// Creates an [ApiError] from any error using the global [StatusMapper]
+    ///
+    /// See [StatusMapper::to_api_error]
+    pub fn from_error<E: Into<BoxError>>(error: E) -> Self {
+        match global_status_mapper() {
+            Some(mapper) => mapper.to_api_error(error.into()),
+            None => StatusMapper::new().to_api_error(error.into()),
+        }
+    }
+}
+
+impl From<BoxError> for ApiError {
+    fn from(error: BoxError) -> Self {
+        ApiError::from_error(error)
+    }
+}
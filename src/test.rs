@@ -55,3 +55,2060 @@ mod serialization {
         assert_eq!(prob.status, None);
     }
 }
+
+#[cfg(feature = "api-error")]
+mod reporting {
+    use crate::*;
+    use std::time::Duration;
+
+    #[test]
+    fn client_errors_are_not_reported() {
+        let reporter = InMemoryReporter::default();
+        let error = ApiError::builder(StatusCode::NOT_FOUND)
+            .extension(ReportTo::new(reporter.clone()))
+            .finish();
+
+        report_server_error(&error);
+
+        assert!(reporter.is_empty());
+    }
+
+    #[test]
+    fn sampled_forwards_one_in_n() {
+        let reporter = InMemoryReporter::default();
+        let sampled = Sampled::one_in(reporter.clone(), 3);
+        let error = ApiError::new(StatusCode::INTERNAL_SERVER_ERROR);
+
+        for _ in 0..7 {
+            sampled.report(&error);
+        }
+
+        assert_eq!(reporter.len(), 3);
+    }
+
+    #[test]
+    fn deduplicated_forwards_each_fingerprint_once() {
+        let reporter = InMemoryReporter::default();
+        let deduplicated = Deduplicated::new(reporter.clone(), Duration::from_secs(60));
+
+        deduplicated.report(&ApiError::new(StatusCode::INTERNAL_SERVER_ERROR));
+        deduplicated.report(&ApiError::new(StatusCode::INTERNAL_SERVER_ERROR));
+        deduplicated.report(&ApiError::new(StatusCode::BAD_GATEWAY));
+
+        let statuses: Vec<_> = reporter.reports().into_iter().map(|r| r.status).collect();
+        assert_eq!(
+            statuses,
+            vec![StatusCode::INTERNAL_SERVER_ERROR, StatusCode::BAD_GATEWAY]
+        );
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn hyper_response_reports_to_the_global_reporter() {
+        let reporter = InMemoryReporter::default();
+        set_global_error_reporter(reporter.clone());
+
+        let response = ApiError::builder(StatusCode::SERVICE_UNAVAILABLE)
+            .message("reported through hyper")
+            .finish()
+            .into_hyper_response();
+        ApiError::builder(StatusCode::NOT_FOUND)
+            .message("not reported through hyper")
+            .finish()
+            .into_hyper_response();
+
+        clear_global_error_reporter();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        // Other tests may report to the global reporter concurrently
+        let messages: Vec<_> = reporter
+            .reports()
+            .into_iter()
+            .filter_map(|r| r.message)
+            .filter(|m| m.ends_with("through hyper"))
+            .collect();
+        assert_eq!(messages, vec!["reported through hyper".to_string()]);
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn hyper_response_reports_to_report_to() {
+        let reporter = InMemoryReporter::default();
+
+        ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+            .extension(ReportTo::new(reporter.clone()))
+            .finish()
+            .into_hyper_response();
+
+        let reports = reporter.reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].status, StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
+
+mod fingerprint {
+    use crate::HttpApiProblem;
+    use http::StatusCode;
+
+    #[test]
+    fn fingerprint_is_stable() {
+        let prob = HttpApiProblem::with_title_and_type(StatusCode::NOT_FOUND)
+            .detail("user 42 does not exist");
+
+        assert_eq!(prob.fingerprint(), "cacd804973b3a79a");
+    }
+
+    #[test]
+    fn selected_fields_become_part_of_the_fingerprint() {
+        let a = HttpApiProblem::new(StatusCode::CONFLICT).value("resource", &"user");
+        let b = HttpApiProblem::new(StatusCode::CONFLICT).value("resource", &"order");
+
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(
+            a.fingerprint_with_fields(&["resource"]),
+            b.fingerprint_with_fields(&["resource"])
+        );
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod backtrace {
+    use std::backtrace::BacktraceStatus;
+
+    use crate::ApiError;
+    use http::StatusCode;
+
+    #[test]
+    fn client_errors_do_not_capture_a_backtrace() {
+        let err = ApiError::new(StatusCode::NOT_FOUND);
+        assert_eq!(err.backtrace().status(), BacktraceStatus::Disabled);
+
+        let err = ApiError::builder(StatusCode::UNPROCESSABLE_ENTITY).finish();
+        assert_eq!(err.backtrace().status(), BacktraceStatus::Disabled);
+    }
+
+    #[test]
+    fn debug_shows_only_the_status_of_the_backtrace() {
+        let err = ApiError::new(StatusCode::INTERNAL_SERVER_ERROR);
+        let debug = format!("{:?}", err);
+
+        assert!(debug.ends_with(&format!("backtrace: {:?} }}", err.backtrace().status())));
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod error_fingerprint {
+    use std::io;
+
+    use crate::*;
+
+    fn internal() -> ApiErrorBuilder {
+        ApiError::builder(StatusCode::INTERNAL_SERVER_ERROR)
+    }
+
+    #[test]
+    fn source_type_is_part_of_the_fingerprint() {
+        let io_a = internal().source(io::Error::other("disk full")).finish();
+        let io_b = internal()
+            .source(io::Error::other("user 17 missing"))
+            .finish();
+        let fmt = internal().source(std::fmt::Error).finish();
+        let none = internal().finish();
+
+        assert_eq!(io_a.fingerprint(), io_b.fingerprint());
+        assert_ne!(io_a.fingerprint(), fmt.fingerprint());
+        assert_ne!(io_a.fingerprint(), none.fingerprint());
+    }
+
+    #[test]
+    fn boxed_sources_are_of_an_unknown_type() {
+        let a = internal()
+            .source_in_a_box("disk full on /dev/sda1")
+            .finish();
+        let b = internal().source_in_a_box("user 17 missing").finish();
+        let replaced = internal()
+            .source(std::fmt::Error)
+            .source_in_a_box("user 17 missing")
+            .finish();
+        let none = internal().finish();
+
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_eq!(a.fingerprint(), replaced.fingerprint());
+        assert_ne!(a.fingerprint(), none.fingerprint());
+
+        let mut set = internal().finish();
+        set.set_source(std::fmt::Error);
+        assert_eq!(
+            set.fingerprint(),
+            internal().source(std::fmt::Error).finish().fingerprint()
+        );
+    }
+
+    #[test]
+    fn source_type_does_not_depend_on_the_extensions() {
+        let mut err = internal().source(std::fmt::Error).finish();
+        let fingerprint = err.fingerprint();
+
+        err.extensions_mut().clear();
+
+        assert_eq!(err.fingerprint(), fingerprint);
+        assert!(err.extensions().is_empty());
+    }
+}
+
+mod typed {
+    use crate::{HttpApiProblem, TypedProblem};
+    use http::StatusCode;
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Credit {
+        balance: u32,
+    }
+
+    #[test]
+    fn typed_fields_are_flattened() {
+        let prob = TypedProblem::new(StatusCode::FORBIDDEN, Credit { balance: 30 }).title("foo");
+
+        let sample = serde_json::to_value(prob).unwrap();
+        let expected = json!({
+            "title": "foo",
+            "status": 403,
+            "balance": 30
+        });
+
+        assert_eq!(sample, expected);
+    }
+
+    #[test]
+    fn without_fields_roundtrips() {
+        let prob: TypedProblem = TypedProblem::from(StatusCode::NOT_FOUND).title("foo");
+
+        let untyped = HttpApiProblem::try_from(prob).unwrap();
+        assert_eq!(
+            untyped,
+            HttpApiProblem::new(StatusCode::NOT_FOUND).title("foo")
+        );
+
+        let typed: TypedProblem = untyped.try_into().unwrap();
+        assert_eq!(typed.title.as_deref(), Some("foo"));
+    }
+
+    #[test]
+    fn missing_typed_fields_fail_the_conversion() {
+        let untyped = HttpApiProblem::new(StatusCode::FORBIDDEN).value("other", &1);
+
+        assert!(TypedProblem::<Credit>::try_from(untyped).is_err());
+    }
+}
+
+mod borrowed {
+    use crate::{HttpApiProblem, HttpApiProblemRef};
+    use http::StatusCode;
+    use serde_json::json;
+    use serde_json::value::RawValue;
+
+    static OUT_OF_CREDIT: HttpApiProblemRef = HttpApiProblemRef::new(StatusCode::FORBIDDEN)
+        .type_url("https://example.com/probs/out-of-credit")
+        .title("You do not have enough credit.")
+        .detail("Your current balance is 30, but that costs 50.")
+        .instance("/account/12345/msgs/abc");
+
+    fn raw(json: &str) -> Box<RawValue> {
+        RawValue::from_string(json.to_string()).unwrap()
+    }
+
+    #[test]
+    fn serializes_like_the_owned_problem() {
+        let balance = raw("30");
+        let accounts = raw(r#"["/account/12345","/account/67890"]"#);
+        let fields = [("balance", &*balance), ("accounts", &*accounts)];
+        let borrowed = OUT_OF_CREDIT.additional_fields(&fields);
+
+        let owned = borrowed.to_http_api_problem();
+
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&borrowed.json_bytes()).unwrap(),
+            serde_json::to_value(&owned).unwrap()
+        );
+        assert_eq!(owned.get_value::<&str, u32>("balance"), Some(30));
+    }
+
+    #[test]
+    fn standard_members_are_not_overwritten_by_fields() {
+        let status = raw("1");
+        let balance = raw("30");
+        let fields = [("status", &*status), ("balance", &*balance)];
+        let borrowed = HttpApiProblemRef::NOT_FOUND.additional_fields(&fields);
+
+        assert_eq!(
+            borrowed.json_string(),
+            r#"{"type":"https://httpstatuses.com/404","status":404,"title":"Not Found","balance":30}"#
+        );
+
+        let owned = borrowed.to_http_api_problem();
+        assert_eq!(owned.status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(owned.json_value("status"), None);
+        assert_eq!(owned.json_value("balance"), Some(&json!(30)));
+    }
+
+    #[test]
+    fn constants_match_the_owned_defaults() {
+        for (borrowed, status) in [
+            (HttpApiProblemRef::BAD_REQUEST, StatusCode::BAD_REQUEST),
+            (HttpApiProblemRef::NOT_FOUND, StatusCode::NOT_FOUND),
+            (
+                HttpApiProblemRef::INTERNAL_SERVER_ERROR,
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ] {
+            assert_eq!(
+                borrowed.to_http_api_problem(),
+                HttpApiProblem::with_title_and_type(status)
+            );
+        }
+    }
+
+    #[cfg(feature = "hyper")]
+    #[test]
+    fn hyper_response_has_the_status_and_media_type() {
+        let response = OUT_OF_CREDIT.to_hyper_response();
+
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(
+            response.headers()["content-type"],
+            crate::PROBLEM_JSON_MEDIA_TYPE
+        );
+        assert_eq!(response.body(), &OUT_OF_CREDIT.json_string());
+    }
+}
+
+mod field_order {
+    use crate::{FieldMap, HttpApiProblem};
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn sorted_serializes_standard_members_first() {
+        let prob = HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .value("zeta", &1)
+            .value("alpha", &2)
+            .instance("/foo")
+            .type_url("/probs/foo");
+
+        assert_eq!(
+            String::from_utf8(prob.json_bytes_sorted()).unwrap(),
+            r#"{"type":"/probs/foo","status":404,"instance":"/foo","alpha":2,"zeta":1}"#
+        );
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn fields_keep_insertion_order() {
+        let prob = HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .value("zeta", &1)
+            .value("alpha", &2)
+            .value("mu", &3);
+
+        assert_eq!(
+            String::from_utf8(prob.json_bytes()).unwrap(),
+            r#"{"status":404,"zeta":1,"alpha":2,"mu":3}"#
+        );
+    }
+
+    #[cfg(feature = "preserve-order")]
+    #[test]
+    fn removing_a_field_keeps_the_order() {
+        let mut prob = HttpApiProblem::new(StatusCode::NOT_FOUND)
+            .value("zeta", &1)
+            .value("alpha", &2)
+            .value("mu", &3);
+
+        prob.additional_fields_mut().remove("zeta");
+
+        let keys: Vec<_> = prob.additional_fields().keys().collect();
+        assert_eq!(keys, vec!["alpha", "mu"]);
+    }
+
+    #[test]
+    fn field_map_api_does_not_depend_on_the_order() {
+        let fields: FieldMap =
+            std::collections::HashMap::from([("a".to_string(), json!(1))]).into();
+        let mut prob = HttpApiProblem::new(StatusCode::NOT_FOUND);
+        prob.additional_fields_mut().extend(fields);
+
+        for (_, value) in prob.additional_fields_mut() {
+            *value = json!(2);
+        }
+
+        assert_eq!(prob.additional_fields().get("a"), Some(&json!(2)));
+        assert_eq!(prob.additional_fields().len(), 1);
+        assert_eq!(
+            serde_json::to_value(prob.additional_fields()).unwrap(),
+            json!({"a": 2})
+        );
+    }
+}
+
+mod parsing {
+    use crate::{HttpApiProblem, ProblemParseError};
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn lenient_ignores_null_members() {
+        let prob = HttpApiProblem::parse_lenient(r#"{"title": null, "status": 500}"#).unwrap();
+
+        assert_eq!(prob, HttpApiProblem::new(StatusCode::INTERNAL_SERVER_ERROR));
+    }
+
+    #[test]
+    fn lenient_drops_invalid_status() {
+        let prob = HttpApiProblem::parse_lenient(r#"{"status": 999}"#).unwrap();
+
+        assert_eq!(prob.status, None);
+        assert_eq!(prob.json_value("status"), None);
+    }
+
+    #[test]
+    fn lenient_result_round_trips_and_is_valid() {
+        let prob = HttpApiProblem::parse_lenient(
+            r#"{"status": 404, "title": 42, "type": ["x"], "detail": "gone", "foo": 1}"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            prob,
+            HttpApiProblem::new(StatusCode::NOT_FOUND)
+                .detail("gone")
+                .value("foo", &1)
+        );
+        assert!(prob.validate().is_empty());
+
+        let json = prob.json_string();
+        let deserialized: HttpApiProblem = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, prob);
+
+        let mut with_title = prob;
+        with_title.title = Some("Not Found".to_string());
+        assert_eq!(with_title.json_string().matches("\"title\"").count(), 1);
+    }
+
+    #[test]
+    fn strict_rejects_string_status() {
+        let err = HttpApiProblem::parse_strict(r#"{"status": "404"}"#).unwrap_err();
+
+        match err {
+            ProblemParseError::InvalidMembers(members) => {
+                assert_eq!(members.len(), 1);
+                assert_eq!(members[0].name, "status");
+                assert_eq!(members[0].value, json!("404"));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn strict_accepts_valid_problem() {
+        let json = r#"{"type": "/probs/x", "status": 404, "title": "t", "foo": [1]}"#;
+
+        let prob = HttpApiProblem::parse_strict(json).unwrap();
+
+        assert_eq!(prob.status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(prob.get_value::<&str, Vec<u8>>("foo"), Some(vec![1]));
+    }
+
+    #[test]
+    fn arrays_are_not_problems() {
+        assert!(matches!(
+            HttpApiProblem::parse_lenient("[]"),
+            Err(ProblemParseError::NotAnObject)
+        ));
+    }
+}
+
+mod conformance {
+    use crate::conformance::is_uri_reference;
+    use crate::{HttpApiProblem, Violation};
+    use http::StatusCode;
+
+    #[test]
+    fn uri_references() {
+        for valid in [
+            "https://example.com/probs/out-of-credit",
+            "/account/12345/msgs/abc",
+            "about:blank",
+            "urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6",
+            "probs/x%20y?a=b#frag",
+            "",
+        ] {
+            assert!(is_uri_reference(valid), "{}", valid);
+        }
+
+        for invalid in [
+            "https://example.com/a b",
+            "1http://example.com",
+            "/probs/%zz",
+            "/a#b#c",
+            "/ümlaut",
+        ] {
+            assert!(!is_uri_reference(invalid), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn reserved_field_names_are_reported() {
+        let mut prob = HttpApiProblem::new(StatusCode::BAD_REQUEST).value("balance", &30);
+        prob.additional_fields_mut()
+            .insert("title".to_string(), 42.into());
+
+        assert_eq!(
+            prob.validate(),
+            vec![Violation::ReservedFieldName("title".into())]
+        );
+    }
+}
+
+mod client {
+    use crate::HttpApiProblem;
+    use http::StatusCode;
+
+    fn response(status: u16, content_type: &str, body: &str) -> http::Response<String> {
+        http::Response::builder()
+            .status(status)
+            .header("Content-Type", content_type)
+            .body(body.to_string())
+            .unwrap()
+    }
+
+    #[test]
+    fn body_status_takes_precedence() {
+        let response = response(500, "application/problem+json", r#"{"status": 503}"#);
+
+        let prob = HttpApiProblem::from_http_response(&response);
+
+        assert_eq!(prob.status, Some(StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[test]
+    fn invalid_json_falls_back_to_status() {
+        let response = response(404, "Application/Problem+JSON", "not json");
+
+        let prob = HttpApiProblem::from_http_response(&response);
+
+        assert_eq!(prob, HttpApiProblem::with_title(StatusCode::NOT_FOUND));
+    }
+
+    #[test]
+    fn parses_problem_xml() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <problem xmlns="urn:ietf:rfc:7807">
+              <type>https://example.com/probs/out-of-credit</type>
+              <title>You do not have enough credit &amp; time.</title>
+              <!-- a comment -->
+              <balance>30</balance>
+              <accounts>
+                <i>https://example.net/account/12345</i>
+              </accounts>
+            </problem>"#;
+        let response = response(403, "application/problem+xml", xml);
+
+        let prob = HttpApiProblem::from_http_response(&response);
+
+        assert_eq!(prob.status, Some(StatusCode::FORBIDDEN));
+        assert_eq!(
+            prob.type_url.as_deref(),
+            Some("https://example.com/probs/out-of-credit")
+        );
+        assert_eq!(
+            prob.title.as_deref(),
+            Some("You do not have enough credit & time.")
+        );
+        assert_eq!(
+            prob.get_value::<&str, String>("balance").as_deref(),
+            Some("30")
+        );
+        assert_eq!(prob.json_value("accounts"), None);
+    }
+
+    fn parse_xml(xml: &str) -> HttpApiProblem {
+        HttpApiProblem::from_http_response(&response(403, "application/problem+xml", xml))
+    }
+
+    #[test]
+    fn xml_markup_in_comments_and_cdata_is_ignored() {
+        let prob = parse_xml(
+            r#"<!-- see <problem> docs --><problem xmlns="urn:ietf:rfc:7807">
+                <title>t</title>
+                <detail><![CDATA[a <b> & c]]></detail>
+                <instance id="x>y"/>
+            </problem>"#,
+        );
+
+        assert_eq!(prob.title.as_deref(), Some("t"));
+        assert_eq!(prob.detail.as_deref(), Some("a <b> & c"));
+        assert_eq!(prob.instance.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn xml_elements_are_matched_by_their_full_local_name() {
+        let prob = parse_xml(
+            r#"<p:problem xmlns:p="urn:ietf:rfc:7807"><p:title>t</p:title><title-x>x</title-x></p:problem>"#,
+        );
+
+        assert_eq!(prob.title.as_deref(), Some("t"));
+        assert_eq!(
+            prob.get_value::<&str, String>("title-x").as_deref(),
+            Some("x")
+        );
+    }
+
+    #[test]
+    fn unsupported_xml_falls_back_to_status() {
+        for xml in [
+            "<problems><title>t</title></problems>",
+            "<problem-x><title>t</title></problem-x>",
+            "<problem><title>t</title>",
+            "<problem><title>t</detail></problem>",
+            "text<problem><title>t</title></problem>",
+            r#"<!DOCTYPE problem [<!ENTITY e "t">]><problem><title>&e;</title></problem>"#,
+        ] {
+            assert_eq!(
+                parse_xml(xml),
+                HttpApiProblem::with_title(StatusCode::FORBIDDEN),
+                "{}",
+                xml
+            );
+        }
+    }
+}
+
+#[cfg(feature = "reqwest")]
+mod reqwest_client {
+    use crate::{ClientProblemError, ReqwestResponseExt};
+    use http::StatusCode;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Serves a single raw HTTP response and returns the URL to request it from
+    async fn serve_once(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 1024];
+            let _ = socket.read(&mut request).await.unwrap();
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.unwrap();
+        });
+
+        format!("http://{}/", addr)
+    }
+
+    #[tokio::test]
+    async fn success_is_passed_through() {
+        let url = serve_once("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok").await;
+
+        let response = reqwest::get(url).await.unwrap().error_for_problem().await;
+
+        assert_eq!(response.unwrap().text().await.unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn problem_is_extracted() {
+        let url = serve_once(
+            "HTTP/1.1 403 Forbidden\r\n\
+             Content-Type: application/problem+json\r\n\
+             X-Request-Id: 42\r\n\
+             Content-Length: 20\r\n\r\n\
+             {\"title\":\"no money\"}",
+        )
+        .await;
+
+        let err = reqwest::get(url)
+            .await
+            .unwrap()
+            .error_for_problem()
+            .await
+            .unwrap_err();
+
+        match err {
+            ClientProblemError::Problem(response) => {
+                assert_eq!(response.status, StatusCode::FORBIDDEN);
+                assert_eq!(response.headers["x-request-id"], "42");
+                assert_eq!(response.problem.status, Some(StatusCode::FORBIDDEN));
+                assert_eq!(response.problem.title.as_deref(), Some("no money"));
+            }
+            err => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_rejected() {
+        let url = serve_once(
+            "HTTP/1.1 409 Conflict\r\n\
+             Content-Type: application/problem+json\r\n\
+             Content-Length: 17\r\n\r\n\
+             {\"title\":\"taken\"}",
+        )
+        .await;
+
+        let err = reqwest::get(url)
+            .await
+            .unwrap()
+            .error_for_problem_with_limit(4)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ClientProblemError::Transport(_)));
+    }
+}
+
+#[cfg(feature = "hyper-client")]
+mod hyper_client {
+    use crate::{ClientProblemError, HyperResponseExt};
+    use http::StatusCode;
+    use http_body_util::{BodyExt, Full};
+    use hyper::body::Bytes;
+
+    fn response(status: StatusCode, body: &'static str) -> hyper::Response<Full<Bytes>> {
+        hyper::Response::builder()
+            .status(status)
+            .header("Content-Type", "application/problem+json")
+            .body(Full::new(Bytes::from_static(body.as_bytes())))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn success_body_is_untouched() {
+        let response = response(StatusCode::OK, "ok").error_for_problem().await;
+
+        let body = response.unwrap().into_body().collect().await.unwrap();
+        assert_eq!(body.to_bytes(), "ok");
+    }
+
+    #[tokio::test]
+    async fn problem_is_extracted() {
+        let err = response(StatusCode::CONFLICT, r#"{"title":"taken"}"#)
+            .error_for_problem()
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.status(), Some(StatusCode::CONFLICT));
+        assert_eq!(err.problem().unwrap().title.as_deref(), Some("taken"));
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_rejected() {
+        let err = response(StatusCode::CONFLICT, r#"{"title":"taken"}"#)
+            .error_for_problem_with_limit(4)
+            .await
+            .unwrap_err();
+
+        assert!(matches!(err, ClientProblemError::Transport(_)));
+    }
+}
+
+mod retry {
+    use crate::{parse_retry_after, HttpApiProblem, RetryAdvice};
+    use http::StatusCode;
+    use std::time::{Duration, UNIX_EPOCH};
+
+    #[test]
+    fn retryable_statuses() {
+        for status in [408, 429, 502, 503, 504] {
+            let prob = HttpApiProblem::try_new(status).unwrap();
+            assert_eq!(prob.retry_advice(), RetryAdvice::Retry { after: None });
+        }
+
+        for status in [400, 404, 500, 501] {
+            let prob = HttpApiProblem::try_new(status).unwrap();
+            assert_eq!(prob.retry_advice(), RetryAdvice::DoNotRetry);
+        }
+    }
+
+    #[test]
+    fn retryable_field_overrides_status() {
+        let prob = HttpApiProblem::new(StatusCode::SERVICE_UNAVAILABLE)
+            .value("retryable", &false)
+            .value("retry_after", &10);
+
+        assert_eq!(prob.retry_advice(), RetryAdvice::DoNotRetry);
+    }
+
+    #[test]
+    fn http_dates_in_the_past_mean_no_delay() {
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        assert_eq!(
+            parse_retry_after("Thu, 01 Jan 1970 00:00:00 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(
+            parse_retry_after("Tue, 14 Nov 2023 22:13:40 GMT", now),
+            Some(Duration::from_secs(20))
+        );
+        assert_eq!(parse_retry_after("tomorrow", now), None);
+    }
+
+    #[test]
+    fn obsolete_http_date_formats_are_accepted() {
+        let now = UNIX_EPOCH + Duration::from_secs(784_111_717);
+        let in_a_minute = Some(Duration::from_secs(60));
+
+        assert_eq!(
+            parse_retry_after("Sunday, 06-Nov-94 08:49:37 GMT", now),
+            in_a_minute
+        );
+        assert_eq!(
+            parse_retry_after("Sun Nov  6 08:49:37 1994", now),
+            in_a_minute
+        );
+
+        let now = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        assert_eq!(
+            parse_retry_after("Tuesday, 14-Nov-23 22:13:40 GMT", now),
+            Some(Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn impossible_http_dates_are_rejected() {
+        let now = UNIX_EPOCH;
+
+        for date in [
+            "Thu, 31 Feb 2000 00:00:00 GMT",
+            "Sat, 29 Feb 1900 00:00:00 GMT",
+            "Mon, 31 Apr 2000 00:00:00 GMT",
+            "Sun, 06 Nov 1994 99:99:99 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:60:00 GMT",
+            "Sun, 06 Nov 1994 08:49:+7 GMT",
+            "Sun, 00 Nov 1994 08:49:37 GMT",
+            "Sun, 06 Nov 94 08:49:37 GMT",
+            "Sun Nov 31 08:49:37 1994",
+        ] {
+            assert_eq!(parse_retry_after(date, now), None, "{}", date);
+        }
+
+        assert!(parse_retry_after("Tue, 29 Feb 2000 00:00:00 GMT", now).is_some());
+        assert!(parse_retry_after("Sat, 31 Dec 2016 23:59:60 GMT", now).is_some());
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn api_error_retry_after_rounds_up() {
+        let prob = crate::ApiError::builder(StatusCode::SERVICE_UNAVAILABLE)
+            .retry_after(Duration::from_millis(1500))
+            .finish()
+            .into_http_api_problem();
+
+        assert_eq!(prob.retry_advice().delay(), Some(Duration::from_secs(2)));
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod upstream {
+    use crate::{
+        ApiError, ClientProblemError, HttpApiProblem, UpstreamExposure, UpstreamPolicy,
+        UpstreamStatus,
+    };
+    use http::StatusCode;
+
+    fn upstream() -> HttpApiProblem {
+        HttpApiProblem::new(StatusCode::CONFLICT)
+            .title("Conflict")
+            .detail("version 3 is outdated")
+            .type_url("https://example.com/probs/outdated")
+            .value("current_version", &4)
+    }
+
+    #[test]
+    fn pass_through_keeps_members_and_original_status() {
+        let err = UpstreamPolicy::new()
+            .exposure(UpstreamExposure::PassThrough)
+            .status(UpstreamStatus::Original)
+            .wrap(upstream());
+
+        let prob = err.into_http_api_problem();
+
+        assert_eq!(prob.status, Some(StatusCode::CONFLICT));
+        assert_eq!(prob.title.as_deref(), Some("Conflict"));
+        assert_eq!(prob.detail.as_deref(), Some("version 3 is outdated"));
+        assert_eq!(
+            prob.type_url.as_deref(),
+            Some("https://example.com/probs/outdated")
+        );
+        assert_eq!(prob.get_value::<&str, u32>("current_version"), Some(4));
+    }
+
+    #[test]
+    fn hide_exposes_nothing_but_keeps_the_upstream_problem() {
+        let err = UpstreamPolicy::new()
+            .exposure(UpstreamExposure::Hide)
+            .wrap(upstream());
+
+        assert_eq!(err.upstream_problem(), Some(&upstream()));
+
+        let prob = err.into_http_api_problem();
+
+        assert_eq!(
+            prob,
+            HttpApiProblem::with_title_and_type(StatusCode::BAD_GATEWAY)
+        );
+    }
+
+    #[test]
+    fn original_status_falls_back_to_bad_gateway() {
+        let err = UpstreamPolicy::new()
+            .status(UpstreamStatus::Original)
+            .wrap(HttpApiProblem::empty());
+
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn transport_errors_become_bad_gateway() {
+        let err: ApiError = ClientProblemError::Transport("connection reset".into()).into();
+
+        assert_eq!(err.status(), StatusCode::BAD_GATEWAY);
+        assert_eq!(err.upstream_problem(), None);
+        assert_eq!(err.detail_message().as_deref(), Some("connection reset"));
+    }
+}
+
+mod collection {
+    use crate::{CollectionStatus, HttpApiProblem, ProblemCollection};
+    use http::StatusCode;
+
+    #[test]
+    fn most_severe_status() {
+        let status = |statuses: &[u16]| {
+            statuses
+                .iter()
+                .map(|s| HttpApiProblem::try_new(*s).unwrap())
+                .collect::<ProblemCollection>()
+                .overall_status()
+        };
+
+        assert_eq!(status(&[404, 404]), StatusCode::NOT_FOUND);
+        assert_eq!(status(&[404, 409]), StatusCode::BAD_REQUEST);
+        assert_eq!(status(&[404, 503]), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(status(&[502, 503, 400]), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(status(&[]), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn truncated_collections_round_trip() {
+        let items: Vec<_> = (0..5)
+            .map(|i| HttpApiProblem::new(StatusCode::CONFLICT).detail(format!("item {}", i)))
+            .collect();
+
+        let problem = ProblemCollection::new()
+            .max_items(3)
+            .status(CollectionStatus::Fixed(StatusCode::MULTI_STATUS))
+            .type_url("https://example.com/probs/import")
+            .problem(items[0].clone())
+            .problem(items[1].clone())
+            .problem(items[2].clone())
+            .problem(items[3].clone())
+            .problem(items[4].clone())
+            .into_http_api_problem()
+            .unwrap();
+
+        let parsed = HttpApiProblem::parse_strict(problem.json_bytes()).unwrap();
+        assert_eq!(parsed.total_errors(), Some(5));
+
+        let (rest, errors) = parsed.split_errors();
+        assert_eq!(errors, items[..3]);
+        assert_eq!(rest.status, Some(StatusCode::MULTI_STATUS));
+        assert_eq!(rest.title.as_deref(), Some("Multi-Status"));
+        assert!(rest.json_value("errors").is_none());
+    }
+
+    #[test]
+    fn empty_collections_have_no_problem() {
+        assert!(ProblemCollection::new().into_http_api_problem().is_none());
+        #[cfg(feature = "api-error")]
+        assert!(ProblemCollection::new().into_api_error().is_none());
+    }
+
+    #[test]
+    fn split_errors_parses_entries_leniently() {
+        let problem = HttpApiProblem::new(StatusCode::BAD_REQUEST).value(
+            "errors",
+            &serde_json::json!([{"status": "404", "title": 42}, "not a problem"]),
+        );
+
+        let (rest, errors) = problem.split_errors();
+        assert_eq!(
+            errors,
+            vec![HttpApiProblem::empty().status(StatusCode::NOT_FOUND)]
+        );
+        assert!(rest.json_value("errors").is_none());
+
+        let problem = HttpApiProblem::new(StatusCode::BAD_REQUEST).value("errors", &"none");
+        let (rest, errors) = problem.split_errors();
+        assert!(errors.is_empty());
+        assert_eq!(rest.json_value("errors"), Some(&serde_json::json!("none")));
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn api_errors_are_collected() {
+        let err = ProblemCollection::new()
+            .title("Import failed")
+            .problem(
+                crate::ApiError::builder(StatusCode::UNPROCESSABLE_ENTITY)
+                    .message("bad name")
+                    .finish(),
+            )
+            .into_api_error()
+            .unwrap();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let (problem, errors) = err.into_http_api_problem().split_errors();
+        assert_eq!(problem.title.as_deref(), Some("Import failed"));
+        assert_eq!(errors[0].detail.as_deref(), Some("bad name"));
+    }
+}
+
+mod validation {
+    use crate::{HttpApiProblem, IssueLocation, ValidationIssue, ValidationProblem};
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn serializes_to_the_rfc_example_shape() {
+        let problem = ValidationProblem::default()
+            .type_url("https://example.net/validation-error")
+            .issue(
+                ValidationIssue::pointer("/age", "must be a positive integer")
+                    .field("code", &"range"),
+            )
+            .header("X-Request-Id", "must be a UUID");
+
+        assert_eq!(
+            serde_json::to_value(&problem).unwrap(),
+            json!({
+                "type": "https://example.net/validation-error",
+                "status": 422,
+                "title": "Your request is not valid.",
+                "errors": [
+                    {"detail": "must be a positive integer", "pointer": "/age", "code": "range"},
+                    {"detail": "must be a UUID", "header": "X-Request-Id"}
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn round_trips_through_http_api_problem() {
+        let problem = ValidationProblem::default()
+            .status(StatusCode::BAD_REQUEST)
+            .parameter("page", "must be a number");
+
+        let untyped = problem.clone().into_http_api_problem();
+        let issues = untyped.validation_issues().unwrap();
+        assert_eq!(issues[0].location(), Some(IssueLocation::Parameter("page")));
+        assert_eq!(issues[0].to_string(), "page: must be a number");
+
+        let typed = ValidationProblem::try_from(untyped).unwrap();
+        assert_eq!(typed, problem);
+    }
+
+    #[test]
+    fn no_issues_without_errors_field() {
+        let problem = HttpApiProblem::new(StatusCode::BAD_REQUEST).value("errors", &"none");
+
+        assert_eq!(problem.validation_issues(), None);
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn converts_into_api_error() {
+        let err: crate::ApiError = ValidationProblem::default()
+            .detail("2 values are invalid")
+            .pointer("/name", "must not be empty")
+            .into();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let problem = err.into_http_api_problem();
+        assert_eq!(problem.detail.as_deref(), Some("2 values are invalid"));
+        assert_eq!(problem.validation_issues().unwrap().len(), 1);
+    }
+}
+
+#[cfg(feature = "validator")]
+mod validator_errors {
+    use std::borrow::Cow;
+
+    use crate::{HttpApiProblem, ValidationProblem};
+    use http::StatusCode;
+    use serde_json::json;
+    use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+    fn errors() -> ValidationErrors {
+        let mut length = ValidationError::new("length").with_message(Cow::from("too short"));
+        length.add_param(Cow::from("min"), &3);
+        length.add_param(Cow::from("value"), &"secret");
+
+        let mut item = ValidationErrors::new();
+        item.add("sku", ValidationError::new("required"));
+
+        let mut address = ValidationErrors::new();
+        address.add("zip/code", ValidationError::new("regex"));
+        address.add("__all__", ValidationError::new("inconsistent"));
+
+        let mut errors = ValidationErrors::new();
+        errors.add("password", length);
+        errors.add("name", ValidationError::new("required"));
+        errors.add("name", ValidationError::new("ascii"));
+        errors.errors_mut().insert(
+            Cow::from("address"),
+            ValidationErrorsKind::Struct(Box::new(address)),
+        );
+        errors.errors_mut().insert(
+            Cow::from("items"),
+            ValidationErrorsKind::List([(2, Box::new(item))].into_iter().collect()),
+        );
+        errors
+    }
+
+    #[test]
+    fn nested_errors_become_sorted_issues() {
+        let problem: HttpApiProblem = errors().into();
+
+        assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert_eq!(
+            problem.json_value("errors").unwrap(),
+            &json!([
+                {"detail": "inconsistent", "pointer": "/address", "code": "inconsistent"},
+                {"detail": "regex", "pointer": "/address/zip~1code", "code": "regex"},
+                {"detail": "required", "pointer": "/items/2/sku", "code": "required"},
+                {"detail": "ascii", "pointer": "/name", "code": "ascii"},
+                {"detail": "required", "pointer": "/name", "code": "required"},
+                {"detail": "too short", "pointer": "/password", "code": "length", "params": {"min": 3}},
+            ])
+        );
+    }
+
+    #[test]
+    fn type_url_is_configurable() {
+        crate::set_validation_type_url("https://example.com/probs/validation");
+        let problem = ValidationProblem::from(errors());
+        crate::clear_validation_type_url();
+
+        assert_eq!(
+            problem.type_url.as_deref(),
+            Some("https://example.com/probs/validation")
+        );
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn converts_into_api_error() {
+        let err: crate::ApiError = errors().into();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            err.into_http_api_problem()
+                .validation_issues()
+                .unwrap()
+                .len(),
+            6
+        );
+    }
+}
+
+#[cfg(feature = "garde")]
+mod garde_report {
+    use crate::HttpApiProblem;
+    use garde::error::NoKey;
+    use garde::{Error, Path, Report};
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn paths_become_sorted_pointers() {
+        let mut report = Report::new();
+        report.append(Path::new("name"), Error::new("not ascii"));
+        report.append(
+            Path::new("tags").join(1).join("a.b/c"),
+            Error::new("too long"),
+        );
+        report.append(
+            Path::new("id").join(NoKey::default()),
+            Error::new("not a UUID"),
+        );
+        report.append(Path::empty(), Error::new("dates are inconsistent"));
+
+        let problem: HttpApiProblem = report.into();
+
+        assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+        assert_eq!(
+            problem.json_value("errors").unwrap(),
+            &json!([
+                {"detail": "dates are inconsistent", "pointer": ""},
+                {"detail": "not a UUID", "pointer": "/id"},
+                {"detail": "not ascii", "pointer": "/name"},
+                {"detail": "too long", "pointer": "/tags/1/a.b~1c"},
+            ])
+        );
+    }
+
+    #[cfg(feature = "validator")]
+    #[test]
+    fn same_shape_as_validator() {
+        let mut report = Report::new();
+        report.append(Path::new("items").join(0), Error::new("invalid"));
+
+        let mut item = validator::ValidationError::new("invalid");
+        item.message = Some("invalid".into());
+        let mut items = validator::ValidationErrors::new();
+        items.add("0", item);
+        let mut errors = validator::ValidationErrors::new();
+        errors.errors_mut().insert(
+            "items".into(),
+            validator::ValidationErrorsKind::Struct(Box::new(items)),
+        );
+
+        let from_garde = crate::ValidationProblem::from(report);
+        let mut from_validator = crate::ValidationProblem::from(errors);
+        from_validator.additional_fields.errors[0]
+            .additional_fields
+            .clear();
+
+        assert_eq!(from_garde.issues(), from_validator.issues());
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn converts_into_api_error() {
+        let mut report = Report::new();
+        report.append(Path::new("name"), Error::new("not ascii"));
+
+        let err: crate::ApiError = report.into();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod json_errors {
+    use crate::ApiError;
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        items: Vec<Item>,
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Item {
+        quantity: u32,
+    }
+
+    #[test]
+    fn errors_are_classified() {
+        let err: ApiError = serde_json::from_str::<Order>("{\"items\": [}")
+            .unwrap_err()
+            .into();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(err.fields().get("line"), Some(&json!(1)));
+
+        let err: ApiError = serde_json::from_str::<Order>("{\"items\": [")
+            .unwrap_err()
+            .into();
+        assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+
+        let err: ApiError = serde_json::from_str::<Order>("{\"items\": 1}")
+            .unwrap_err()
+            .into();
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(err.fields().get("line"), None);
+        assert_eq!(
+            err.fields().get("errors"),
+            Some(&json!([{
+                "detail": "invalid type: integer `1`, expected a sequence",
+                "line": 1,
+                "column": 11,
+            }]))
+        );
+
+        let io = std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset");
+        let err: ApiError = serde_json::Error::io(io).into();
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(err.fields().is_empty());
+    }
+
+    #[test]
+    fn unknown_positions_are_left_out() {
+        let err: ApiError = serde_json::from_value::<Order>(json!({"items": 1}))
+            .unwrap_err()
+            .into();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            err.fields().get("errors"),
+            Some(&json!([{"detail": "invalid type: integer `1`, expected a sequence"}]))
+        );
+    }
+
+    #[cfg(feature = "serde_path_to_error")]
+    #[test]
+    fn path_becomes_pointer() {
+        let de = &mut serde_json::Deserializer::from_str(
+            "{\"items\": [{\"quantity\": 1}, {\"quantity\": -1}]}",
+        );
+        let err: ApiError = serde_path_to_error::deserialize::<_, Order>(de)
+            .unwrap_err()
+            .into();
+
+        assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(err.fields().get("pointer"), None);
+
+        let issues = err.into_http_api_problem().validation_issues().unwrap();
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].pointer.as_deref(), Some("/items/1/quantity"));
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod io_status {
+    use std::io::{self, ErrorKind};
+
+    use crate::{default_io_error_status, ApiError};
+    use http::StatusCode;
+
+    #[test]
+    fn kinds_are_mapped() {
+        let status = |kind: ErrorKind| default_io_error_status(&io::Error::from(kind));
+
+        assert_eq!(status(ErrorKind::NotFound), StatusCode::NOT_FOUND);
+        assert_eq!(status(ErrorKind::PermissionDenied), StatusCode::FORBIDDEN);
+        assert_eq!(status(ErrorKind::AlreadyExists), StatusCode::CONFLICT);
+        assert_eq!(status(ErrorKind::InvalidData), StatusCode::BAD_REQUEST);
+        assert_eq!(status(ErrorKind::TimedOut), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(status(ErrorKind::Other), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[cfg(rustc_1_83)]
+    #[test]
+    fn recent_kinds_are_mapped() {
+        let status = |kind: ErrorKind| default_io_error_status(&io::Error::from(kind));
+
+        assert_eq!(
+            status(ErrorKind::FileTooLarge),
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+        assert_eq!(status(ErrorKind::NetworkDown), StatusCode::BAD_GATEWAY);
+        assert_eq!(
+            status(ErrorKind::StorageFull),
+            StatusCode::INSUFFICIENT_STORAGE
+        );
+    }
+
+    #[test]
+    fn only_server_errors_get_the_io_title() {
+        let err: ApiError = io::Error::new(ErrorKind::NotFound, "no such file").into();
+        assert_eq!(err.title(), None);
+        assert_eq!(err.message(), None);
+
+        let err: ApiError = io::Error::other("disk on fire").into();
+        assert_eq!(err.title(), Some("An IO error occurred"));
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod status_mapper {
+    use std::error::Error;
+    use std::fmt;
+    use std::io;
+
+    use crate::{ApiError, StatusMapper, StatusMapping};
+    use http::StatusCode;
+
+    #[derive(Debug)]
+    struct Wrapper(Box<dyn Error + Send + Sync>);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "operation failed")
+        }
+    }
+
+    impl Error for Wrapper {
+        fn source(&self) -> Option<&(dyn Error + 'static)> {
+            Some(&*self.0)
+        }
+    }
+
+    #[derive(Debug)]
+    struct Conflict(u32);
+
+    impl fmt::Display for Conflict {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "version {} is outdated", self.0)
+        }
+    }
+
+    impl Error for Conflict {}
+
+    fn mapper() -> StatusMapper {
+        StatusMapper::new()
+            .map_type_with(|conflict: &Conflict| {
+                (conflict.0 > 0).then(|| {
+                    StatusMapping::new(StatusCode::CONFLICT)
+                        .type_url("https://example.com/probs/outdated")
+                })
+            })
+            .map_if(
+                |err| err.to_string().contains("quota"),
+                StatusCode::TOO_MANY_REQUESTS,
+            )
+    }
+
+    #[test]
+    fn source_chain_is_walked() {
+        let err = mapper().to_api_error(Box::new(Wrapper(Box::new(Conflict(3)))));
+
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+        assert_eq!(err.type_url(), Some("https://example.com/probs/outdated"));
+        assert_eq!(err.detail_message().as_deref(), Some("operation failed"));
+    }
+
+    #[test]
+    fn rules_fall_through_to_predicates_and_defaults() {
+        let err = mapper().to_api_error(Box::new(Conflict(0)));
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let err = mapper().to_api_error("quota exceeded".into());
+        assert_eq!(err.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn api_errors_and_io_errors_are_recognized() {
+        let inner = ApiError::builder(StatusCode::GONE)
+            .title("Gone for good")
+            .finish();
+        let err = StatusMapper::new().to_api_error(Box::new(Wrapper(Box::new(inner))));
+        assert_eq!(err.status(), StatusCode::GONE);
+        assert_eq!(err.title(), Some("Gone for good"));
+
+        let io = io::Error::from(io::ErrorKind::PermissionDenied);
+        let err = StatusMapper::new().to_api_error(Box::new(Wrapper(Box::new(io))));
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn boxed_api_errors_are_unboxed() {
+        let boxed: Box<dyn Error + Send + Sync> = Box::new(
+            ApiError::builder(StatusCode::NOT_FOUND)
+                .message("no user")
+                .finish(),
+        );
+
+        let err: ApiError = boxed.into();
+
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.message(), Some("no user"));
+        assert!(err.source().is_none());
+    }
+}
+
+#[cfg(feature = "anyhow")]
+mod anyhow_error {
+    use std::error::Error;
+    use std::io;
+
+    use crate::{AnyhowStatusExt, ApiError};
+    use anyhow::Context;
+    use http::StatusCode;
+
+    #[test]
+    fn hint_deep_in_the_stack_is_found() {
+        let result: anyhow::Result<()> = Err(io::Error::other("row missing"))
+            .with_status(StatusCode::NOT_FOUND)
+            .context("loading the profile");
+
+        let err: ApiError = result.unwrap_err().into();
+
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.message(), Some("loading the profile"));
+
+        let chain: Vec<String> = std::iter::successors(err.source(), |e| (*e).source())
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            chain,
+            vec!["loading the profile", "404 Not Found", "row missing"]
+        );
+    }
+
+    #[test]
+    fn chain_is_classified_without_hint() {
+        let inner = ApiError::builder(StatusCode::CONFLICT).finish();
+        let err: ApiError = anyhow::Error::new(inner).context("saving the order").into();
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+
+        let err: ApiError = anyhow::anyhow!("something broke").into();
+        assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+
+    #[test]
+    fn api_errors_without_context_are_unwrapped() {
+        let err: ApiError =
+            anyhow::Error::new(ApiError::builder(StatusCode::GONE).message("gone").finish()).into();
+
+        assert_eq!(err.status(), StatusCode::GONE);
+        assert_eq!(err.message(), Some("gone"));
+        assert!(err.source().is_none());
+    }
+}
+
+#[cfg(feature = "eyre")]
+mod eyre_report {
+    use std::io;
+
+    use crate::{ApiError, EyreStatusExt};
+    use eyre::WrapErr;
+    use http::StatusCode;
+
+    #[test]
+    fn hint_deep_in_the_stack_is_found() {
+        let result: eyre::Result<()> = Err(io::Error::other("row missing"))
+            .wrap_status(StatusCode::NOT_FOUND)
+            .wrap_err("loading the profile");
+
+        let err: ApiError = result.unwrap_err().into();
+
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.message(), Some("loading the profile"));
+    }
+
+    #[test]
+    fn io_errors_in_the_chain_are_mapped() {
+        let err: ApiError = eyre::Report::new(io::Error::from(io::ErrorKind::PermissionDenied))
+            .wrap_err("reading the config")
+            .into();
+
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+    }
+}
+
+#[cfg(all(feature = "anyhow", feature = "eyre"))]
+mod anyhow_and_eyre {
+    use std::io;
+
+    use crate::*;
+
+    #[test]
+    fn both_extensions_can_be_used_together() {
+        let result: anyhow::Result<()> =
+            Err(io::Error::other("row missing")).with_status(StatusCode::NOT_FOUND);
+        let err: ApiError = result.unwrap_err().into();
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+
+        let result: eyre::Result<()> =
+            Err(io::Error::other("row missing")).wrap_status(StatusCode::GONE);
+        let err: ApiError = result.unwrap_err().into();
+        assert_eq!(err.status(), StatusCode::GONE);
+    }
+}
+
+#[cfg(feature = "api-error")]
+mod result_ext {
+    use std::error::Error;
+    use std::io;
+
+    use crate::{ApiError, ApiOptionExt, ApiResultExt};
+    use http::StatusCode;
+    use serde_json::json;
+
+    #[test]
+    fn or_not_found_describes_what_is_missing() {
+        let err = Err::<(), _>(io::Error::other("no rows"))
+            .or_not_found(format_args!("user {}", 42))
+            .unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert_eq!(err.message(), Some("user 42 not found"));
+        assert_eq!(err.source().unwrap().to_string(), "no rows");
+    }
+
+    #[test]
+    fn api_errors_are_not_wrapped() {
+        let err = Err::<(), _>(ApiError::new(StatusCode::CONFLICT))
+            .or_status(StatusCode::BAD_REQUEST)
+            .unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::CONFLICT);
+        assert!(err.source().is_none());
+    }
+
+    #[test]
+    fn with_problem_sees_the_error() {
+        let err = Err::<(), _>(io::Error::from(io::ErrorKind::TimedOut))
+            .with_problem(|e| {
+                ApiError::builder(StatusCode::GATEWAY_TIMEOUT)
+                    .field("kind", format!("{:?}", e.kind()))
+            })
+            .unwrap_err();
+
+        assert_eq!(err.status(), StatusCode::GATEWAY_TIMEOUT);
+        assert_eq!(err.fields().get("kind"), Some(&json!("TimedOut")));
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn context_field_keeps_the_classification() {
+        let err = Err::<(), _>(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context_field("path", "/etc/shadow")
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::FORBIDDEN);
+        assert_eq!(err.fields().get("path"), Some(&json!("/etc/shadow")));
+
+        let err = Err::<(), _>(ApiError::new(StatusCode::GONE))
+            .context_field("id", 7)
+            .unwrap_err();
+        assert_eq!(err.status(), StatusCode::GONE);
+        assert_eq!(err.fields().get("id"), Some(&json!(7)));
+    }
+
+    #[test]
+    fn source_type_is_kept_for_the_fingerprint() {
+        let io = |message: &str| Err::<(), _>(io::Error::other(message.to_owned()));
+        let fmt = || Err::<(), _>(std::fmt::Error);
+
+        let status = StatusCode::SERVICE_UNAVAILABLE;
+        let a = io("disk full").or_status(status).unwrap_err();
+        let b = io("user 17 missing").or_status(status).unwrap_err();
+        assert_eq!(a.fingerprint(), b.fingerprint());
+        assert_ne!(
+            a.fingerprint(),
+            fmt().or_status(status).unwrap_err().fingerprint()
+        );
+
+        assert_ne!(
+            io("x").or_not_found("user").unwrap_err().fingerprint(),
+            fmt().or_not_found("user").unwrap_err().fingerprint()
+        );
+        assert_ne!(
+            io("x").context_field("id", 7).unwrap_err().fingerprint(),
+            fmt().context_field("id", 7).unwrap_err().fingerprint()
+        );
+    }
+
+    #[test]
+    fn none_becomes_an_api_error() {
+        assert_eq!(Some(1).ok_or_not_found().unwrap(), 1);
+        assert_eq!(
+            None::<u32>
+                .ok_or_status(StatusCode::GONE)
+                .unwrap_err()
+                .status(),
+            StatusCode::GONE
+        );
+    }
+}
+
+mod macros {
+    use crate::*;
+
+    #[test]
+    fn problem_accepts_literal_and_status_code() {
+        assert_eq!(
+            problem!(404),
+            HttpApiProblem::with_title(StatusCode::NOT_FOUND)
+        );
+        assert_eq!(
+            problem!(StatusCode::NOT_FOUND),
+            HttpApiProblem::with_title(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[test]
+    fn problem_with_detail_and_fields() {
+        let id = 42;
+        let p = problem!(404, "User {} not found", id; field "user_id" = id, field "kind" = "user");
+
+        assert_eq!(p.status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(p.detail.as_deref(), Some("User 42 not found"));
+        assert_eq!(p.get_value::<&str, u32>("user_id"), Some(42));
+        assert_eq!(p.get_value::<&str, String>("kind").as_deref(), Some("user"));
+
+        let p = problem!(StatusCode::GONE; field "id" = 7);
+        assert_eq!(p.detail, None);
+        assert_eq!(p.get_value::<&str, u32>("id"), Some(7));
+    }
+
+    #[cfg(feature = "api-error")]
+    #[allow(clippy::result_large_err)]
+    mod api_error {
+        use crate::*;
+        use serde_json::json;
+
+        #[test]
+        fn api_error_with_message_and_fields() {
+            let id = 42;
+            let err = api_error!(404, "User {id} not found"; field "user_id" = id);
+
+            assert_eq!(err.status(), StatusCode::NOT_FOUND);
+            assert_eq!(err.message(), Some("User 42 not found"));
+            assert_eq!(err.fields().get("user_id"), Some(&json!(42)));
+
+            let err = api_error!(StatusCode::CONFLICT);
+            assert_eq!(err.status(), StatusCode::CONFLICT);
+            assert_eq!(err.message(), None);
+        }
+
+        fn check(value: i32) -> Result<i32, ApiError> {
+            ensure_api!(value >= 0, 400, "{} is negative", value);
+            if value > 100 {
+                bail_api!(StatusCode::PAYLOAD_TOO_LARGE; field "max" = 100);
+            }
+            Ok(value)
+        }
+
+        #[test]
+        fn bail_and_ensure_return_early() {
+            assert_eq!(check(5).unwrap(), 5);
+
+            let err = check(-1).unwrap_err();
+            assert_eq!(err.status(), StatusCode::BAD_REQUEST);
+            assert_eq!(err.message(), Some("-1 is negative"));
+
+            let err = check(101).unwrap_err();
+            assert_eq!(err.status(), StatusCode::PAYLOAD_TOO_LARGE);
+            assert_eq!(err.fields().get("max"), Some(&json!(100)));
+        }
+
+        #[test]
+        fn bail_converts_into_the_error_type() {
+            fn run() -> Result<(), HttpApiProblem> {
+                bail_api!(503, "maintenance");
+            }
+
+            let p = run().unwrap_err();
+            assert_eq!(p.status, Some(StatusCode::SERVICE_UNAVAILABLE));
+            assert_eq!(p.detail.as_deref(), Some("maintenance"));
+        }
+    }
+}
+
+#[cfg(feature = "sqlx")]
+mod sqlx_error {
+    use std::error::Error;
+
+    use crate::*;
+
+    #[test]
+    fn row_not_found_is_404() {
+        let err: ApiError = sqlx::Error::RowNotFound.into();
+
+        assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn pool_timeout_is_503() {
+        let err: ApiError = sqlx::Error::PoolTimedOut.into();
+        assert_eq!(err.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[cfg(feature = "test-sqlite")]
+    mod sqlite {
+        use std::borrow::Cow;
+        use std::error::Error;
+
+        use serde_json::json;
+        use sqlx::{Connection, Executor, SqliteConnection};
+
+        use crate::*;
+
+        async fn connection() -> SqliteConnection {
+            let mut conn = SqliteConnection::connect("sqlite::memory:").await.unwrap();
+            conn.execute(
+                "PRAGMA foreign_keys = ON;
+                CREATE TABLE users (
+                    id INTEGER PRIMARY KEY,
+                    email TEXT NOT NULL UNIQUE,
+                    age INTEGER NOT NULL CONSTRAINT age_positive CHECK (age > 0)
+                );
+                CREATE TABLE posts (
+                    id INTEGER PRIMARY KEY,
+                    user_id INTEGER NOT NULL REFERENCES users (id)
+                );
+                INSERT INTO users (id, email, age) VALUES (1, 'anna@example.com', 30);
+                INSERT INTO posts (id, user_id) VALUES (1, 1);",
+            )
+            .await
+            .unwrap();
+            conn
+        }
+
+        async fn fail(conn: &mut SqliteConnection, sql: &str) -> ApiError {
+            conn.execute(sql).await.unwrap_err().into()
+        }
+
+        #[tokio::test]
+        async fn fetch_one_without_row_is_404() {
+            let mut conn = connection().await;
+
+            let err: ApiError = sqlx::query_scalar::<_, i64>("SELECT id FROM users WHERE id = 2")
+                .fetch_one(&mut conn)
+                .await
+                .unwrap_err()
+                .into();
+
+            assert_eq!(err.status(), StatusCode::NOT_FOUND);
+        }
+
+        #[tokio::test]
+        async fn constraint_violations_are_classified() {
+            let mut conn = connection().await;
+
+            let err = fail(
+                &mut conn,
+                "INSERT INTO users (email, age) VALUES ('anna@example.com', 20)",
+            )
+            .await;
+            assert_eq!(err.status(), StatusCode::CONFLICT);
+
+            let err = fail(
+                &mut conn,
+                "INSERT INTO users (email, age) VALUES ('bob@example.com', 0)",
+            )
+            .await;
+            assert_eq!(err.status(), StatusCode::UNPROCESSABLE_ENTITY);
+
+            // SQLite does not tell a missing from a still referenced record apart
+            let err = fail(&mut conn, "DELETE FROM users WHERE id = 1").await;
+            assert_eq!(err.status(), StatusCode::CONFLICT);
+            let err = fail(&mut conn, "INSERT INTO posts (user_id) VALUES (2)").await;
+            assert_eq!(err.status(), StatusCode::CONFLICT);
+        }
+
+        #[tokio::test]
+        async fn other_errors_are_500_without_the_database_message() {
+            let mut conn = connection().await;
+
+            let err = fail(&mut conn, "SELECT * FROM missing").await;
+
+            assert_eq!(err.status(), StatusCode::INTERNAL_SERVER_ERROR);
+            assert_eq!(err.message(), Some("A database error occurred"));
+            assert!(err.source().unwrap().to_string().contains("missing"));
+        }
+
+        #[tokio::test]
+        async fn constraint_exposure_is_configurable() {
+            let mut conn = connection().await;
+            let sql = "INSERT INTO users (email, age) VALUES ('bob@example.com', -1)";
+
+            let err = fail(&mut conn, sql).await;
+            assert!(err.fields().is_empty());
+
+            set_sqlx_constraint_exposure(ConstraintExposure::Member(Cow::Borrowed(
+                CONSTRAINT_FIELD,
+            )));
+            let err = fail(&mut conn, sql).await;
+            assert_eq!(
+                err.fields().get(CONSTRAINT_FIELD),
+                Some(&json!("age_positive"))
+            );
+
+            set_sqlx_constraint_exposure(ConstraintExposure::Member(Cow::Borrowed("violated")));
+            let err = fail(&mut conn, sql).await;
+            assert_eq!(err.fields().get("violated"), Some(&json!("age_positive")));
+            assert_eq!(err.fields().get(CONSTRAINT_FIELD), None);
+
+            set_sqlx_constraint_exposure(ConstraintExposure::Hide);
+            let err = fail(&mut conn, sql).await;
+            assert!(err.fields().is_empty());
+
+            clear_sqlx_constraint_exposure();
+        }
+    }
+}
+
+#[cfg(feature = "tonic")]
+mod tonic_status {
+    use tonic::Code;
+
+    use crate::*;
+
+    #[test]
+    fn codes_are_mapped_in_both_directions() {
+        for (status, code) in [
+            (StatusCode::BAD_REQUEST, Code::InvalidArgument),
+            (StatusCode::UNAUTHORIZED, Code::Unauthenticated),
+            (StatusCode::FORBIDDEN, Code::PermissionDenied),
+            (StatusCode::NOT_FOUND, Code::NotFound),
+            (StatusCode::CONFLICT, Code::AlreadyExists),
+            (StatusCode::TOO_MANY_REQUESTS, Code::ResourceExhausted),
+            (StatusCode::INTERNAL_SERVER_ERROR, Code::Internal),
+            (StatusCode::NOT_IMPLEMENTED, Code::Unimplemented),
+            (StatusCode::SERVICE_UNAVAILABLE, Code::Unavailable),
+            (StatusCode::GATEWAY_TIMEOUT, Code::DeadlineExceeded),
+        ] {
+            assert_eq!(grpc_code_from_status(status), code);
+            assert_eq!(status_from_grpc_code(code), status);
+        }
+
+        assert_eq!(
+            grpc_code_from_status(StatusCode::UNPROCESSABLE_ENTITY),
+            Code::InvalidArgument
+        );
+        assert_eq!(
+            grpc_code_from_status(StatusCode::GONE),
+            Code::FailedPrecondition
+        );
+        assert_eq!(
+            grpc_code_from_status(StatusCode::BAD_GATEWAY),
+            Code::Unavailable
+        );
+        assert_eq!(status_from_grpc_code(Code::Cancelled).as_u16(), 499);
+        assert_eq!(
+            grpc_code_from_status(StatusCode::from_u16(499).unwrap()),
+            Code::Cancelled
+        );
+    }
+
+    #[test]
+    fn problem_survives_the_round_trip() {
+        let problem = HttpApiProblem::with_title_and_type(StatusCode::CONFLICT)
+            .detail("version 3 is outdated")
+            .instance("/orders/7")
+            .value("current", &4);
+
+        let status = problem.to_grpc_status();
+        assert_eq!(status.code(), Code::AlreadyExists);
+        assert_eq!(status.message(), "version 3 is outdated");
+        assert!(status
+            .metadata()
+            .get_bin(PROBLEM_DETAILS_METADATA_KEY)
+            .is_some());
+
+        assert_eq!(HttpApiProblem::from(status), problem);
+    }
+
+    #[test]
+    fn status_without_problem_is_mapped_by_code() {
+        let status = tonic::Status::not_found("no user 42");
+
+        let problem = HttpApiProblem::from(status);
+
+        assert_eq!(
+            problem,
+            HttpApiProblem::with_title(StatusCode::NOT_FOUND).detail("no user 42")
+        );
+    }
+
+    #[test]
+    fn problem_without_status_is_internal() {
+        let status = tonic::Status::from(HttpApiProblem::empty().title("Oops"));
+
+        assert_eq!(status.code(), Code::Internal);
+        assert_eq!(status.message(), "Oops");
+    }
+
+    #[cfg(feature = "api-error")]
+    #[test]
+    fn api_error_survives_the_round_trip() {
+        let error = || {
+            ApiError::builder(StatusCode::NOT_FOUND)
+                .message("no user 42")
+                .field("user_id", 42)
+                .finish()
+        };
+        let problem = error().into_http_api_problem();
+
+        let status = tonic::Status::from(error());
+        assert_eq!(status.code(), Code::NotFound);
+
+        let error = ApiError::from(status);
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert_eq!(error.message(), Some("no user 42"));
+        assert_eq!(error.into_http_api_problem(), problem);
+    }
+}
+
+#[cfg(feature = "url")]
+mod resolve {
+    use url::Url;
+
+    use crate::*;
+
+    fn base() -> Url {
+        Url::parse("https://example.com/account/12345/").unwrap()
+    }
+
+    #[test]
+    fn absolute_references_stay_untouched() {
+        let mut prob = HttpApiProblem::new(StatusCode::FORBIDDEN)
+            .type_url("https://example.com")
+            .instance("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6");
+
+        prob.resolve_against(&base()).unwrap();
+
+        assert_eq!(prob.type_url.as_deref(), Some("https://example.com"));
+        assert_eq!(
+            prob.instance.as_deref(),
+            Some("urn:uuid:f81d4fae-7dec-11d0-a765-00a0c91e6bf6")
+        );
+    }
+
+    #[test]
+    fn about_blank_stays_untouched() {
+        let mut prob = HttpApiProblem::new(StatusCode::FORBIDDEN).type_url("about:blank");
+
+        prob.resolve_against(&base()).unwrap();
+
+        assert_eq!(prob.type_url.as_deref(), Some("about:blank"));
+    }
+
+    #[test]
+    fn relative_references_are_resolved() {
+        let mut prob = HttpApiProblem::new(StatusCode::FORBIDDEN)
+            .type_url("../../problems/out-of-credit")
+            .instance("msgs/abc?x=1#frag");
+
+        prob.resolve_against(&base()).unwrap();
+
+        assert_eq!(
+            prob.type_url.as_deref(),
+            Some("https://example.com/problems/out-of-credit")
+        );
+        assert_eq!(
+            prob.instance.as_deref(),
+            Some("https://example.com/account/12345/msgs/abc?x=1#frag")
+        );
+    }
+
+    #[test]
+    fn missing_members_stay_missing() {
+        let mut prob = HttpApiProblem::new(StatusCode::FORBIDDEN);
+
+        prob.resolve_against(&base()).unwrap();
+
+        assert_eq!(prob, HttpApiProblem::new(StatusCode::FORBIDDEN));
+    }
+
+    #[test]
+    fn request_url_is_the_base() {
+        let request_url = Url::parse("https://a.example/api/orders").unwrap();
+        let mut prob = HttpApiProblem::new(StatusCode::FORBIDDEN).type_url("x");
+
+        prob.resolve_for_response(&request_url).unwrap();
+
+        assert_eq!(prob.type_url.as_deref(), Some("https://a.example/api/x"));
+    }
+}
@@ -0,0 +1,206 @@
+//! Conversions between problems and [tonic::Status].
+//!
+//! Services which offer both REST and gRPC can use the same errors
+//! for both. The status of a problem is mapped to a [tonic::Code] with
+//! [grpc_code_from_status] and back with [status_from_grpc_code].
+//!
+//! The problem itself is carried as JSON in the binary metadata
+//! [PROBLEM_DETAILS_METADATA_KEY] so that a gateway in front of a gRPC
+//! service can rebuild it without loss. The message of the [tonic::Status]
+//! is the `detail` or the `title` of the problem.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let problem = HttpApiProblem::with_title(StatusCode::NOT_FOUND)
+//!     .detail("no user 42")
+//!     .value("user_id", &42);
+//!
+//! let status = tonic::Status::from(problem.clone());
+//! assert_eq!(status.code(), tonic::Code::NotFound);
+//! assert_eq!(status.message(), "no user 42");
+//!
+//! assert_eq!(HttpApiProblem::from(status), problem);
+//! ```
+use tonic::metadata::MetadataValue;
+use tonic::Code;
+
+use super::*;
+
+/// The binary metadata key carrying the problem as JSON
+pub const PROBLEM_DETAILS_METADATA_KEY: &str = "problem-details-bin";
+
+/// Maps a [StatusCode] to a [tonic::Code]
+///
+/// | Status | Code |
+/// |--------|------|
+/// | `400`, `422` | `InvalidArgument` |
+/// | `401` | `Unauthenticated` |
+/// | `403` | `PermissionDenied` |
+/// | `404` | `NotFound` |
+/// | `409` | `AlreadyExists` |
+/// | `412` | `FailedPrecondition` |
+/// | `416` | `OutOfRange` |
+/// | `429` | `ResourceExhausted` |
+/// | `499` | `Cancelled` |
+/// | other `4xx` | `FailedPrecondition` |
+/// | `501` | `Unimplemented` |
+/// | `502`, `503` | `Unavailable` |
+/// | `504` | `DeadlineExceeded` |
+/// | other `5xx` | `Internal` |
+/// | all others | `Unknown` |
+pub fn grpc_code_from_status(status: StatusCode) -> Code {
+    match status.as_u16() {
+        400 | 422 => Code::InvalidArgument,
+        401 => Code::Unauthenticated,
+        403 => Code::PermissionDenied,
+        404 => Code::NotFound,
+        409 => Code::AlreadyExists,
+        412 => Code::FailedPrecondition,
+        416 => Code::OutOfRange,
+        429 => Code::ResourceExhausted,
+        499 => Code::Cancelled,
+        400..=499 => Code::FailedPrecondition,
+        501 => Code::Unimplemented,
+        502 | 503 => Code::Unavailable,
+        504 => Code::DeadlineExceeded,
+        500..=599 => Code::Internal,
+        _ => Code::Unknown,
+    }
+}
+
+/// Maps a [tonic::Code] to a [StatusCode]
+///
+/// | Code | Status |
+/// |------|--------|
+/// | `Ok` | `200` |
+/// | `InvalidArgument`, `FailedPrecondition`, `OutOfRange` | `400` |
+/// | `Unauthenticated` | `401` |
+/// | `PermissionDenied` | `403` |
+/// | `NotFound` | `404` |
+/// | `AlreadyExists`, `Aborted` | `409` |
+/// | `ResourceExhausted` | `429` |
+/// | `Cancelled` | `499` |
+/// | `Unknown`, `Internal`, `DataLoss` | `500` |
+/// | `Unimplemented` | `501` |
+/// | `Unavailable` | `503` |
+/// | `DeadlineExceeded` | `504` |
+pub fn status_from_grpc_code(code: Code) -> StatusCode {
+    match code {
+        Code::Ok => StatusCode::OK,
+        Code::InvalidArgument | Code::FailedPrecondition | Code::OutOfRange => {
+            StatusCode::BAD_REQUEST
+        }
+        Code::Unauthenticated => StatusCode::UNAUTHORIZED,
+        Code::PermissionDenied => StatusCode::FORBIDDEN,
+        Code::NotFound => StatusCode::NOT_FOUND,
+        Code::AlreadyExists | Code::Aborted => StatusCode::CONFLICT,
+        Code::ResourceExhausted => StatusCode::TOO_MANY_REQUESTS,
+        Code::Cancelled => StatusCode::from_u16(499).expect("499 is a valid status"),
+        Code::Unknown | Code::Internal | Code::DataLoss => StatusCode::INTERNAL_SERVER_ERROR,
+        Code::Unimplemented => StatusCode::NOT_IMPLEMENTED,
+        Code::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        Code::DeadlineExceeded => StatusCode::GATEWAY_TIMEOUT,
+    }
+}
+
+/// Returns the problem carried in the metadata of a [tonic::Status]
+fn problem_from_metadata(status: &tonic::Status) -> Option<HttpApiProblem> {
+    let bytes = status
+        .metadata()
+        .get_bin(PROBLEM_DETAILS_METADATA_KEY)?
+        .to_bytes()
+        .ok()?;
+    HttpApiProblem::parse_lenient(&bytes).ok()
+}
+
+impl HttpApiProblem {
+    /// Creates a [tonic::Status] which carries the problem in its metadata
+    ///
+    /// A problem without a status becomes an `Internal` error.
+    ///
+    /// Requires the `tonic` feature
+    pub fn to_grpc_status(&self) -> tonic::Status {
+        let code = self
+            .status
+            .map(grpc_code_from_status)
+            .unwrap_or(Code::Internal);
+        let message = self
+            .detail
+            .as_deref()
+            .or(self.title.as_deref())
+            .unwrap_or_default();
+
+        let mut status = tonic::Status::new(code, message);
+        status.metadata_mut().insert_bin(
+            PROBLEM_DETAILS_METADATA_KEY,
+            MetadataValue::from_bytes(&self.json_bytes()),
+        );
+        status
+    }
+
+    /// Rebuilds a problem from a [tonic::Status]
+    ///
+    /// If the status carries a problem in its metadata that problem is
+    /// returned. Otherwise a problem is created from the code and the
+    /// message of the status.
+    ///
+    /// Requires the `tonic` feature
+    pub fn from_grpc_status(status: &tonic::Status) -> Self {
+        if let Some(problem) = problem_from_metadata(status) {
+            return problem;
+        }
+
+        let mut problem = HttpApiProblem::with_title(status_from_grpc_code(status.code()));
+        if !status.message().is_empty() {
+            problem.detail = Some(status.message().to_owned());
+        }
+        problem
+    }
+}
+
+impl From<HttpApiProblem> for tonic::Status {
+    fn from(problem: HttpApiProblem) -> Self {
+        problem.to_grpc_status()
+    }
+}
+
+impl From<tonic::Status> for HttpApiProblem {
+    fn from(status: tonic::Status) -> Self {
+        HttpApiProblem::from_grpc_status(&status)
+    }
+}
+
+#[cfg(feature = "api-error")]
+impl From<ApiError> for tonic::Status {
+    /// Server errors are handed to the [ErrorReporter]
+    fn from(error: ApiError) -> Self {
+        report_server_error(&error);
+        error.into_http_api_problem().to_grpc_status()
+    }
+}
+
+#[cfg(feature = "api-error")]
+impl From<tonic::Status> for ApiError {
+    /// The members of the rebuilt problem become the members of the error
+    ///
+    /// The [tonic::Status] is not set as the source since its `Display`
+    /// contains the metadata.
+    fn from(status: tonic::Status) -> Self {
+        let problem = HttpApiProblem::from_grpc_status(&status);
+
+        let mut builder = ApiError::builder(
+            problem
+                .status
+                .unwrap_or_else(|| status_from_grpc_code(status.code())),
+        );
+        builder.title = problem.title;
+        builder.message = problem.detail;
+        builder.type_url = problem.type_url;
+        builder.instance = problem.instance;
+        builder.fields = problem.additional_fields;
+        builder.finish()
+    }
+}
@@ -0,0 +1,194 @@
+//! A problem with strongly typed additional fields.
+//!
+//! [HttpApiProblem] stores its additional fields as JSON values which
+//! means that every field has to be (de)serialized individually and
+//! type errors only show up at runtime. A [TypedProblem] instead carries
+//! a struct whose fields are flattened into the problem object.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct OutOfCredit {
+//!     balance: u32,
+//!     accounts: Vec<String>,
+//! }
+//!
+//! let p = TypedProblem::new(
+//!     StatusCode::FORBIDDEN,
+//!     OutOfCredit {
+//!         balance: 30,
+//!         accounts: vec!["/account/12345".into()],
+//!     },
+//! )
+//! .title("You do not have enough credit.")
+//! .type_url("https://example.com/probs/out-of-credit");
+//!
+//! let untyped: HttpApiProblem = p.try_into().unwrap();
+//! assert_eq!(Some(30), untyped.get_value::<&str, u32>("balance"));
+//!
+//! let typed: TypedProblem<OutOfCredit> = untyped.try_into().unwrap();
+//! assert_eq!(30, typed.additional_fields.balance);
+//! ```
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+#[cfg(feature = "json-schema")]
+use schemars::JsonSchema;
+
+use super::*;
+
+/// A problem as described by [RFC7807](https://tools.ietf.org/html/rfc7807)
+/// whose additional fields are given by the type `E`.
+///
+/// The fields of `E` are flattened into the problem object so `E` should
+/// serialize to a JSON object. The default `()` adds no fields at all.
+///
+/// Conversions from and to an [HttpApiProblem] are available
+/// via [TryFrom].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+#[cfg_attr(
+    feature = "json-schema",
+    schemars(
+        description = "Description of a problem that can be returned by an HTTP API based on [RFC7807](https://tools.ietf.org/html/rfc7807)"
+    )
+)]
+pub struct TypedProblem<E = ()> {
+    /// A URI reference that identifies the problem type.
+    ///
+    /// See [HttpApiProblem::type_url]
+    #[serde(rename = "type")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub type_url: Option<String>,
+
+    /// The HTTP status code generated by the origin server for this
+    /// occurrence of the problem.
+    #[serde(default)]
+    #[serde(with = "crate::custom_http_status_serialization")]
+    #[cfg_attr(feature = "json-schema", schemars(with = "Option<u16>"))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<StatusCode>,
+
+    /// A short, human-readable summary of the problem type.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+
+    /// A human-readable explanation specific to this
+    /// occurrence of the problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+
+    /// A URI reference that identifies the specific
+    /// occurrence of the problem.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instance: Option<String>,
+
+    /// The typed additional fields
+    ///
+    /// These values get serialized into the JSON
+    /// on top level.
+    #[serde(flatten)]
+    pub additional_fields: E,
+}
+
+impl<E> TypedProblem<E> {
+    /// Creates a new instance with the given [StatusCode] and additional fields.
+    pub fn new<T: Into<StatusCode>>(status: T, additional_fields: E) -> Self {
+        Self::empty(additional_fields).status(status)
+    }
+
+    /// Creates a new instance with only the additional fields set.
+    pub fn empty(additional_fields: E) -> Self {
+        Self {
+            type_url: None,
+            status: None,
+            title: None,
+            detail: None,
+            instance: None,
+            additional_fields,
+        }
+    }
+
+    /// Sets the `status`
+    pub fn status<T: Into<StatusCode>>(mut self, status: T) -> Self {
+        self.status = Some(status.into());
+        self
+    }
+
+    /// Sets the `type_url`
+    pub fn type_url<T: Into<String>>(mut self, type_url: T) -> Self {
+        self.type_url = Some(type_url.into());
+        self
+    }
+
+    /// Sets the `title`
+    pub fn title<T: Into<String>>(mut self, title: T) -> Self {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// Sets the `detail`
+    pub fn detail<T: Into<String>>(mut self, detail: T) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Sets the `instance`
+    pub fn instance<T: Into<String>>(mut self, instance: T) -> Self {
+        self.instance = Some(instance.into());
+        self
+    }
+
+    /// Replaces the additional fields
+    pub fn additional_fields<F>(self, additional_fields: F) -> TypedProblem<F> {
+        TypedProblem {
+            type_url: self.type_url,
+            status: self.status,
+            title: self.title,
+            detail: self.detail,
+            instance: self.instance,
+            additional_fields,
+        }
+    }
+}
+
+impl<E: Serialize> TypedProblem<E> {
+    /// Serialize to a JSON `Vec<u8>`
+    ///
+    /// Fails if the additional fields can not be serialized.
+    pub fn json_bytes(&self) -> Result<Vec<u8>, serde_json::Error> {
+        serde_json::to_vec(self)
+    }
+
+    /// Serialize to a JSON `String`
+    ///
+    /// Fails if the additional fields can not be serialized.
+    pub fn json_string(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string_pretty(self)
+    }
+}
+
+impl<E: Serialize> TryFrom<TypedProblem<E>> for HttpApiProblem {
+    type Error = serde_json::Error;
+
+    fn try_from(problem: TypedProblem<E>) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::to_value(problem)?)
+    }
+}
+
+impl<E: DeserializeOwned> TryFrom<HttpApiProblem> for TypedProblem<E> {
+    type Error = serde_json::Error;
+
+    fn try_from(problem: HttpApiProblem) -> Result<Self, Self::Error> {
+        serde_json::from_value(serde_json::to_value(problem)?)
+    }
+}
+
+impl From<StatusCode> for TypedProblem {
+    fn from(status: StatusCode) -> Self {
+        TypedProblem::new(status, ())
+    }
+}
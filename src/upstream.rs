@@ -0,0 +1,155 @@
+//! Propagation of problems received from upstream services.
+//!
+//! A gateway which receives a problem from a service it called has to decide
+//! with which status and how much of the problem it passes on to its own
+//! clients. An [UpstreamPolicy] captures this decision and turns the upstream
+//! problem into an [ApiError].
+//!
+//! The upstream problem is always kept as an [UpstreamProblem] extension of
+//! the [ApiError] so that it can be logged regardless of the policy.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let upstream = HttpApiProblem::with_title(StatusCode::NOT_FOUND).detail("no user 42");
+//!
+//! let error = UpstreamPolicy::new()
+//!     .exposure(UpstreamExposure::Nest)
+//!     .wrap(upstream.clone());
+//!
+//! assert_eq!(error.status(), StatusCode::BAD_GATEWAY);
+//! assert_eq!(error.upstream_problem(), Some(&upstream));
+//!
+//! let problem = error.into_http_api_problem();
+//! assert_eq!(
+//!     problem.get_value::<&str, HttpApiProblem>("cause"),
+//!     Some(upstream)
+//! );
+//! ```
+use super::*;
+
+/// How much of an upstream problem becomes visible to clients
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpstreamExposure {
+    /// The members of the upstream problem become the members of the error
+    PassThrough,
+    /// The upstream problem is nested in the field `cause`
+    #[default]
+    Nest,
+    /// Nothing of the upstream problem is visible
+    Hide,
+}
+
+/// The status of an error created from an upstream problem
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UpstreamStatus {
+    /// `502 - Bad Gateway`
+    #[default]
+    BadGateway,
+    /// The status of the upstream problem
+    ///
+    /// Falls back to `502 - Bad Gateway` if the upstream problem has no status.
+    Original,
+    /// A fixed status
+    Fixed(StatusCode),
+}
+
+/// An extension of an [ApiError] containing the problem received
+/// from an upstream service.
+///
+/// Never part of an [HttpApiProblem].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamProblem(pub HttpApiProblem);
+
+/// Decides how an upstream problem is turned into an [ApiError].
+///
+/// The default nests the upstream problem and responds with
+/// `502 - Bad Gateway`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct UpstreamPolicy {
+    pub exposure: UpstreamExposure,
+    pub status: UpstreamStatus,
+}
+
+impl UpstreamPolicy {
+    /// Creates the default policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the [UpstreamExposure]
+    pub fn exposure(mut self, exposure: UpstreamExposure) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Sets the [UpstreamStatus]
+    pub fn status(mut self, status: UpstreamStatus) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Turns the upstream problem into an [ApiError] according to this policy.
+    ///
+    /// The upstream problem is not set as the source of the error since
+    /// the source would be used as the message. It can be retrieved via
+    /// [ApiError::upstream_problem].
+    pub fn wrap(&self, problem: HttpApiProblem) -> ApiError {
+        let status = match self.status {
+            UpstreamStatus::BadGateway => StatusCode::BAD_GATEWAY,
+            UpstreamStatus::Original => problem.status.unwrap_or(StatusCode::BAD_GATEWAY),
+            UpstreamStatus::Fixed(status) => status,
+        };
+
+        let mut builder = ApiError::builder(status);
+
+        match self.exposure {
+            UpstreamExposure::PassThrough => {
+                builder.title.clone_from(&problem.title);
+                builder.message.clone_from(&problem.detail);
+                builder.type_url.clone_from(&problem.type_url);
+                builder.instance.clone_from(&problem.instance);
+                for (key, value) in problem.additional_fields() {
+                    builder.fields.insert(key.clone(), value.clone());
+                }
+            }
+            UpstreamExposure::Nest => {
+                builder = builder.field("cause", &problem);
+            }
+            UpstreamExposure::Hide => {}
+        }
+
+        builder.extension(UpstreamProblem(problem)).finish()
+    }
+}
+
+impl ApiError {
+    /// Turns an upstream problem into an [ApiError] using the
+    /// default [UpstreamPolicy].
+    pub fn from_upstream(problem: HttpApiProblem) -> Self {
+        UpstreamPolicy::default().wrap(problem)
+    }
+
+    /// Returns the problem received from an upstream service if there is one
+    pub fn upstream_problem(&self) -> Option<&HttpApiProblem> {
+        self.extensions()
+            .get::<UpstreamProblem>()
+            .map(|upstream| &upstream.0)
+    }
+}
+
+impl From<ClientProblemError> for ApiError {
+    /// Problems are wrapped using the default [UpstreamPolicy].
+    /// Transport errors become a `502 - Bad Gateway` with the
+    /// transport error as the source.
+    fn from(err: ClientProblemError) -> Self {
+        match err {
+            ClientProblemError::Problem(response) => ApiError::from_upstream(response.problem),
+            ClientProblemError::Transport(err) => ApiError::builder(StatusCode::BAD_GATEWAY)
+                .source_in_a_box(err)
+                .finish(),
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! A problem type for invalid requests.
+//!
+//! A [ValidationProblem] has the shape of the example in
+//! [RFC 9457, Section 3](https://www.rfc-editor.org/rfc/rfc9457#section-3):
+//! the member `errors` lists [ValidationIssue]s, each with a `detail` and
+//! the location of the invalid value as a JSON Pointer into the request body
+//! (`pointer`, in the plain form `/age` created by [json_pointer] and the
+//! integrations), the name of a query parameter (`parameter`) or the name of
+//! a header (`header`).
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//!
+//! let problem = ValidationProblem::default()
+//!     .pointer("/age", "must be a positive integer")
+//!     .parameter("page", "must be a number")
+//!     .into_http_api_problem();
+//!
+//! assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+//!
+//! let issues = problem.validation_issues().unwrap();
+//! assert_eq!(issues[0].location(), Some(IssueLocation::Pointer("/age")));
+//! assert_eq!(issues[1].detail, "must be a number");
+//! ```
+use std::fmt;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "json-schema")]
+use schemars::JsonSchema;
+
+use super::*;
+
+/// The title of a [ValidationProblem] created with [ValidationProblem::default]
+pub const VALIDATION_TITLE: &str = "Your request is not valid.";
+
+static VALIDATION_TYPE_URL: RwLock<Option<String>> = RwLock::new(None);
+
+/// Sets the `type` of the validation problems created from the errors
+/// of validation libraries like `validator` or `garde` and from
+/// `serde_json` errors for invalid data.
+pub fn set_validation_type_url<T: Into<String>>(type_url: T) {
+    let mut guard = VALIDATION_TYPE_URL
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = Some(type_url.into());
+}
+
+/// Removes the `type` set with [set_validation_type_url].
+pub fn clear_validation_type_url() {
+    let mut guard = VALIDATION_TYPE_URL
+        .write()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    *guard = None;
+}
+
+#[cfg(any(feature = "validator", feature = "garde", feature = "api-error"))]
+pub(crate) fn validation_type_url() -> Option<String> {
+    VALIDATION_TYPE_URL
+        .read()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// A single invalid value of a request
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+pub struct ValidationIssue {
+    /// A human-readable explanation of what is wrong with the value
+    pub detail: String,
+
+    /// A JSON Pointer to the invalid value in the request body
+    ///
+    /// The pointer is in its plain form like `/items/0/name` as created by
+    /// [json_pointer], not in the URI fragment form `#/items/0/name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pointer: Option<String>,
+
+    /// The name of the invalid query parameter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parameter: Option<String>,
+
+    /// The name of the invalid header
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub header: Option<String>,
+
+    /// Additional fields like an error code
+    #[serde(flatten)]
+    pub additional_fields: FieldMap,
+}
+
+/// The location of a [ValidationIssue]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueLocation<'a> {
+    /// A JSON Pointer into the request body
+    Pointer(&'a str),
+    /// The name of a query parameter
+    Parameter(&'a str),
+    /// The name of a header
+    Header(&'a str),
+}
+
+impl ValidationIssue {
+    /// Creates an issue without a location
+    pub fn new<T: Into<String>>(detail: T) -> Self {
+        Self {
+            detail: detail.into(),
+            pointer: None,
+            parameter: None,
+            header: None,
+            additional_fields: Default::default(),
+        }
+    }
+
+    /// Creates an issue for the value at the JSON Pointer in the request body
+    pub fn pointer<P: Into<String>, T: Into<String>>(pointer: P, detail: T) -> Self {
+        let mut issue = Self::new(detail);
+        issue.pointer = Some(pointer.into());
+        issue
+    }
+
+    /// Creates an issue for a query parameter
+    pub fn parameter<P: Into<String>, T: Into<String>>(parameter: P, detail: T) -> Self {
+        let mut issue = Self::new(detail);
+        issue.parameter = Some(parameter.into());
+        issue
+    }
+
+    /// Creates an issue for a header
+    pub fn header<H: Into<String>, T: Into<String>>(header: H, detail: T) -> Self {
+        let mut issue = Self::new(detail);
+        issue.header = Some(header.into());
+        issue
+    }
+
+    /// Adds an additional field
+    ///
+    /// If the value can not be serialized it is not added.
+    pub fn field<K: Into<String>, V: Serialize>(mut self, key: K, value: &V) -> Self {
+        if let Ok(value) = serde_json::to_value(value) {
+            self.additional_fields.insert(key.into(), value);
+        }
+        self
+    }
+
+    /// Returns the location of the invalid value if there is one
+    ///
+    /// A pointer takes precedence over a parameter and
+    /// a parameter over a header.
+    pub fn location(&self) -> Option<IssueLocation<'_>> {
+        if let Some(pointer) = self.pointer.as_deref() {
+            Some(IssueLocation::Pointer(pointer))
+        } else if let Some(parameter) = self.parameter.as_deref() {
+            Some(IssueLocation::Parameter(parameter))
+        } else {
+            self.header.as_deref().map(IssueLocation::Header)
+        }
+    }
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.location() {
+            Some(IssueLocation::Pointer(location))
+            | Some(IssueLocation::Parameter(location))
+            | Some(IssueLocation::Header(location)) => write!(f, "{}: {}", location, self.detail),
+            None => write!(f, "{}", self.detail),
+        }
+    }
+}
+
+/// The issues of a [ValidationProblem]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "json-schema", derive(JsonSchema))]
+pub struct ValidationIssues {
+    /// The invalid values
+    #[serde(default)]
+    pub errors: Vec<ValidationIssue>,
+}
+
+/// A problem describing the invalid values of a request
+///
+/// The issues are listed in the member `errors`. [ValidationProblem::default]
+/// creates a problem with the status `422 - Unprocessable Entity`, the title
+/// [VALIDATION_TITLE] and no issues.
+pub type ValidationProblem = TypedProblem<ValidationIssues>;
+
+impl Default for ValidationProblem {
+    fn default() -> Self {
+        TypedProblem::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ValidationIssues::default(),
+        )
+        .title(VALIDATION_TITLE)
+    }
+}
+
+impl ValidationProblem {
+    /// Adds an issue
+    pub fn issue(mut self, issue: ValidationIssue) -> Self {
+        self.push(issue);
+        self
+    }
+
+    /// Adds an issue for the value at the JSON Pointer in the request body
+    pub fn pointer<P: Into<String>, T: Into<String>>(self, pointer: P, detail: T) -> Self {
+        self.issue(ValidationIssue::pointer(pointer, detail))
+    }
+
+    /// Adds an issue for a query parameter
+    pub fn parameter<P: Into<String>, T: Into<String>>(self, parameter: P, detail: T) -> Self {
+        self.issue(ValidationIssue::parameter(parameter, detail))
+    }
+
+    /// Adds an issue for a header
+    pub fn header<H: Into<String>, T: Into<String>>(self, header: H, detail: T) -> Self {
+        self.issue(ValidationIssue::header(header, detail))
+    }
+
+    /// Adds an issue
+    pub fn push(&mut self, issue: ValidationIssue) {
+        self.additional_fields.errors.push(issue);
+    }
+
+    /// Returns the issues
+    pub fn issues(&self) -> &[ValidationIssue] {
+        &self.additional_fields.errors
+    }
+
+    /// Returns `true` if there are no issues
+    pub fn is_empty(&self) -> bool {
+        self.additional_fields.errors.is_empty()
+    }
+
+    /// Creates an [HttpApiProblem] with the issues in the field `errors`
+    ///
+    /// Unlike the conversion via [TryFrom] this can not fail.
+    pub fn into_http_api_problem(self) -> HttpApiProblem {
+        let mut problem = HttpApiProblem::empty();
+        problem.type_url = self.type_url;
+        problem.status = self.status;
+        problem.title = self.title;
+        problem.detail = self.detail;
+        problem.instance = self.instance;
+        problem.set_value(ERRORS_FIELD, &self.additional_fields.errors);
+        problem
+    }
+
+    /// Creates an [ApiError] with the issues in the field `errors`
+    ///
+    /// A missing status becomes `422 - Unprocessable Entity`.
+    ///
+    /// Requires the `api-error` feature
+    #[cfg(feature = "api-error")]
+    pub fn into_api_error(self) -> ApiError {
+        let status = self.status.unwrap_or(StatusCode::UNPROCESSABLE_ENTITY);
+        let mut builder =
+            ApiError::builder(status).field(ERRORS_FIELD, self.additional_fields.errors);
+        builder.type_url = self.type_url;
+        builder.title = self.title;
+        builder.message = self.detail;
+        builder.instance = self.instance;
+        builder.finish()
+    }
+}
+
+impl Extend<ValidationIssue> for ValidationProblem {
+    fn extend<I: IntoIterator<Item = ValidationIssue>>(&mut self, iter: I) {
+        self.additional_fields.errors.extend(iter);
+    }
+}
+
+impl FromIterator<ValidationIssue> for ValidationProblem {
+    fn from_iter<I: IntoIterator<Item = ValidationIssue>>(iter: I) -> Self {
+        let mut problem = Self::default();
+        problem.extend(iter);
+        problem
+    }
+}
+
+#[cfg(feature = "api-error")]
+impl From<ValidationProblem> for ApiError {
+    fn from(problem: ValidationProblem) -> Self {
+        problem.into_api_error()
+    }
+}
+
+impl HttpApiProblem {
+    /// Returns the issues of a validation problem
+    ///
+    /// Returns `None` if the field `errors` is missing or is not
+    /// a list of [ValidationIssue]s.
+    pub fn validation_issues(&self) -> Option<Vec<ValidationIssue>> {
+        self.get_value::<&str, _>(ERRORS_FIELD)
+    }
+}
+
+/// Creates a JSON Pointer ([RFC 6901](https://tools.ietf.org/html/rfc6901))
+/// from the segments of a path.
+///
+/// `~` and `/` in the segments are escaped.
+///
+/// #Example
+///
+/// ```rust
+/// use http_api_problem::*;
+///
+/// assert_eq!(json_pointer(["items", "0", "a/b"]), "/items/0/a~1b");
+/// assert_eq!(json_pointer(Vec::<String>::new()), "");
+/// ```
+pub fn json_pointer<I, S>(segments: I) -> String
+where
+    I: IntoIterator<Item = S>,
+    S: fmt::Display,
+{
+    let mut pointer = String::new();
+    for segment in segments {
+        pointer.push('/');
+        for c in segment.to_string().chars() {
+            match c {
+                '~' => pointer.push_str("~0"),
+                '/' => pointer.push_str("~1"),
+                c => pointer.push(c),
+            }
+        }
+    }
+    pointer
+}
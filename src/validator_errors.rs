@@ -0,0 +1,132 @@
+//! Conversion of errors of the `validator` crate into validation problems.
+//!
+//! Every failed validation becomes a [ValidationIssue] with a JSON Pointer
+//! to the invalid value, the error code in the field `code` and the
+//! parameters of the validation in the field `params`. The issues are sorted
+//! by pointer and code so that the output does not depend on the iteration
+//! order of the maps of `validator`.
+//!
+//! The parameter `value` which `validator` adds automatically is left out
+//! since it echoes the rejected input which might be a secret.
+//!
+//! Requires the `validator` feature
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_api_problem::*;
+//! use validator::{ValidationError, ValidationErrors};
+//!
+//! let mut errors = ValidationErrors::new();
+//! errors.add("name", ValidationError::new("length"));
+//!
+//! let problem: HttpApiProblem = errors.into();
+//!
+//! assert_eq!(problem.status, Some(StatusCode::UNPROCESSABLE_ENTITY));
+//!
+//! let issues = problem.validation_issues().unwrap();
+//! assert_eq!(issues[0].pointer.as_deref(), Some("/name"));
+//! assert_eq!(issues[0].detail, "length");
+//! ```
+use validator::{ValidationErrors, ValidationErrorsKind};
+
+use super::*;
+
+/// The key `validator` uses for errors of a whole struct
+const STRUCT_ERRORS_KEY: &str = "__all__";
+
+impl ValidationProblem {
+    /// Creates a validation problem from [ValidationErrors]
+    ///
+    /// The `type` is the one set with [set_validation_type_url].
+    ///
+    /// Requires the `validator` feature
+    pub fn from_validation_errors(errors: &ValidationErrors) -> Self {
+        let mut issues = Vec::new();
+        collect_issues(errors, &mut Vec::new(), &mut issues);
+        issues.sort_by(|a, b| {
+            (
+                a.pointer.as_deref(),
+                a.additional_fields.get("code").and_then(Value::as_str),
+            )
+                .cmp(&(
+                    b.pointer.as_deref(),
+                    b.additional_fields.get("code").and_then(Value::as_str),
+                ))
+        });
+
+        let mut problem: ValidationProblem = issues.into_iter().collect();
+        problem.type_url = validation_type_url();
+        problem
+    }
+}
+
+fn collect_issues(
+    errors: &ValidationErrors,
+    path: &mut Vec<String>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for (key, kind) in errors.errors() {
+        let is_struct_error = key == STRUCT_ERRORS_KEY;
+        if !is_struct_error {
+            path.push(key.to_string());
+        }
+
+        match kind {
+            ValidationErrorsKind::Struct(errors) => collect_issues(errors, path, issues),
+            ValidationErrorsKind::List(errors) => {
+                for (idx, errors) in errors {
+                    path.push(idx.to_string());
+                    collect_issues(errors, path, issues);
+                    path.pop();
+                }
+            }
+            ValidationErrorsKind::Field(errors) => {
+                for error in errors {
+                    let detail = error.message.as_deref().unwrap_or(&error.code);
+                    let mut issue = ValidationIssue::pointer(json_pointer(path.iter()), detail)
+                        .field("code", &error.code);
+
+                    let mut params: Vec<_> = error
+                        .params
+                        .iter()
+                        .filter(|(name, _)| *name != "value")
+                        .collect();
+                    if !params.is_empty() {
+                        params.sort_by(|a, b| a.0.cmp(b.0));
+                        let params: serde_json::Map<String, Value> = params
+                            .into_iter()
+                            .map(|(name, value)| (name.to_string(), value.clone()))
+                            .collect();
+                        issue = issue.field("params", &params);
+                    }
+
+                    issues.push(issue);
+                }
+            }
+        }
+
+        if !is_struct_error {
+            path.pop();
+        }
+    }
+}
+
+impl From<ValidationErrors> for ValidationProblem {
+    fn from(errors: ValidationErrors) -> Self {
+        ValidationProblem::from_validation_errors(&errors)
+    }
+}
+
+impl From<ValidationErrors> for HttpApiProblem {
+    fn from(errors: ValidationErrors) -> Self {
+        ValidationProblem::from_validation_errors(&errors).into_http_api_problem()
+    }
+}
+
+#[cfg(feature = "api-error")]
+impl From<ValidationErrors> for ApiError {
+    fn from(errors: ValidationErrors) -> Self {
+        ValidationProblem::from_validation_errors(&errors).into_api_error()
+    }
+}